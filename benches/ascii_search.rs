@@ -0,0 +1,22 @@
+use greplite::search;
+use std::time::Instant;
+
+fn main() {
+    let line = "2024-01-01T00:00:00Z INFO request handled in 12ms path=/api/v1/items\n";
+    let mut content = line.repeat(200_000);
+    content.push_str("2024-01-01T00:00:01Z ERROR Connection Reset by peer\n");
+
+    let ascii = Instant::now();
+    let results = search("connection reset", &content, true, &None, None);
+    let ascii_elapsed = ascii.elapsed();
+    assert_eq!(results.len(), 1);
+
+    let non_ascii = content.replace("INFO", "INFÖ");
+    let unicode = Instant::now();
+    let results = search("connection reset", &non_ascii, true, &None, None);
+    let unicode_elapsed = unicode.elapsed();
+    assert_eq!(results.len(), 1);
+
+    println!("ascii -i search:   {:?}", ascii_elapsed);
+    println!("unicode -i search: {:?}", unicode_elapsed);
+}
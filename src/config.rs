@@ -1,6 +1,38 @@
-use crate::error::ApplicationError;
+use crate::{error::ApplicationError, highlight::HighlightStyle, types::extensions_for};
+use std::{env, fs, time::Duration};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Path,
+    Modified,
+    Size,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchOrder {
+    Text,
+    Numeric,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// `--color=always`: highlight whatever the environment says.
+    Always,
+    /// `-c`: highlight unless `NO_COLOR` or `CLICOLOR=0` turns it off.
+    On,
+    /// `--color=auto`: like `On`, but only when stdout is a terminal.
+    Auto,
+    #[default]
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStyle {
+    Absolute,
+    Relative(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct Config {
     pub query: String,
     pub file_paths: Vec<String>,
@@ -10,15 +42,367 @@ pub struct Config {
     pub enable_highlighting: bool,
     pub read_from_stdin: bool,
     pub recursive_search: bool,
+    pub heading: bool,
+    pub trim: bool,
+    pub sort_by: Option<SortBy>,
+    pub range: Option<(usize, usize)>,
+    pub skip_files: Option<String>,
+    pub section: Option<String>,
+    pub section_name: Option<String>,
+    pub count_files: bool,
+    pub count_chars: bool,
+    pub match_histogram: bool,
+    pub verbose: bool,
+    pub quiet: bool,
+    pub json_array: bool,
+    pub pattern_file: Option<String>,
+    pub multiline: bool,
+    pub show_byte_offset: bool,
+    pub global_numbers: bool,
+    pub align_numbers: bool,
+    pub relative_numbers: bool,
+    pub fixed_strings: bool,
+    pub tab_size: Option<usize>,
+    pub max_columns: Option<usize>,
+    pub preview_match: bool,
+    pub print_first_match: bool,
+    pub files_without_match: bool,
+    pub null_terminated: bool,
+    pub regex_size_limit: Option<usize>,
+    pub dfa_size_limit: Option<usize>,
+    pub timeout: Option<Duration>,
+    pub suppress_errors: bool,
+    pub extract_group: Option<usize>,
+    pub extract_name: Option<String>,
+    pub first_per_line: bool,
+    pub stdin_label: Option<String>,
+    pub auto_recursive: bool,
+    pub follow_links: bool,
+    pub hidden: bool,
+    pub binary_as_text: bool,
+    pub binary_text_preview: bool,
+    pub preprocess_cmd: Option<String>,
+    pub color: ColorChoice,
+    pub passthrough: bool,
+    pub path_style: Option<PathStyle>,
+    pub min_match_len: Option<usize>,
+    pub fuzzy: Option<usize>,
+    pub max_count: Option<usize>,
+    pub name_only: bool,
+    pub comment_char: Option<char>,
+    pub field: Option<usize>,
+    pub delimiter: Option<char>,
+    pub unique: bool,
+    pub dim_context: bool,
+    pub replace: Option<String>,
+    pub in_place: bool,
+    pub in_place_backup: Option<String>,
+    pub replace_dry_run: bool,
+    pub top: Option<usize>,
+    pub line_numbers_only: bool,
+    pub slurp: bool,
+    pub file_types: Vec<String>,
+    pub excluded_types: Vec<String>,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub max_results: Option<usize>,
+    pub word_regexp: bool,
+    pub ascii_word: bool,
+    pub sort_matches: Option<MatchOrder>,
+    pub reverse: bool,
+    pub invert_match: bool,
+    pub context_first_only: bool,
+    pub trim_context_blank: bool,
+    pub indent_context: usize,
+    pub regex_flags: Option<String>,
+    pub no_unicode: bool,
+    pub match_separator: Option<String>,
+    pub context_separator: Option<String>,
+    pub output_separator: Option<String>,
+    pub head_bytes: Option<usize>,
+    pub count: bool,
+    pub count_distinct: bool,
+    pub files_with_count: bool,
+    pub files_with_count_sorted: bool,
+    pub count_nonzero: bool,
+    pub exit_on_error: bool,
+    pub newer_than: Option<Duration>,
+    pub older_than: Option<Duration>,
+    pub progress: bool,
+    pub no_glob: bool,
+    pub name_pattern: Option<String>,
+    pub inline_input: Option<String>,
+    pub multi_match_only: bool,
+    pub highlight_style: HighlightStyle,
+}
+
+/// Names a config file to read when `--config` isn't given. There is no implicit config file, so
+/// a directory can't change how greplite behaves just by being the current directory.
+const CONFIG_PATH_VAR: &str = "GREPLITE_CONFIG_PATH";
+
+/// Config file keys for flags that run commands or rewrite files. A config file only sets
+/// search and display defaults, so these have to be given on the command line.
+const UNSAFE_CONFIG_KEYS: &[&str] = &["pre", "replace", "in_place", "in_place_backup", "config"];
+
+/// Flags that read the next argument as their value. These aren't allowed in `GREPLITE_OPTS`,
+/// where a trailing one would swallow the first command-line argument.
+const VALUE_FLAGS: &[&str] = &[
+    "-f",
+    "--file",
+    "-M",
+    "--max-columns",
+    "-A",
+    "--after-context",
+    "-B",
+    "--before-context",
+    "-C",
+    "--context",
+    "--indent-context",
+    "--range",
+    "--comment-char",
+    "--field",
+    "--delimiter",
+    "--tabsize",
+    "--type",
+    "--type-not",
+    "--max-results",
+    "--top",
+    "--fuzzy",
+    "--min-len",
+    "--regex-size-limit",
+    "--head-bytes",
+    "--dfa-size-limit",
+    "--timeout",
+    "--input",
+    "--name-pattern",
+    "--newer-than",
+    "--older-than",
+    "--match-separator",
+    "--context-separator",
+    "--output-separator",
+    "--regex-flags",
+    "--extract",
+    "--extract-name",
+    "--label",
+    "--path-relative",
+    "--replace",
+    "--in-place-backup",
+    "--pre",
+    "--skip-files",
+    "--section",
+    "--section-name",
+    "--config",
+];
+
+/// Removes `--config PATH` from `args` and returns the flags set in that file, or in the file
+/// named by `GREPLITE_CONFIG_PATH` (read through `lookup`). Without either, no file is read.
+fn read_config_file<F: Fn(&str) -> Option<String>>(
+    args: &mut Vec<String>,
+    lookup: &F,
+) -> Result<Vec<String>, ApplicationError> {
+    let path = match args.iter().position(|arg| arg == "--config") {
+        Some(index) if index + 1 < args.len() => {
+            let path = args.remove(index + 1);
+            args.remove(index);
+            path
+        }
+        Some(_) => return Err(ApplicationError::InvalidFlag("--config".to_string())),
+        None => match lookup(CONFIG_PATH_VAR).filter(|path| !path.is_empty()) {
+            Some(path) => path,
+            None => return Ok(Vec::new()),
+        },
+    };
+
+    let content = fs::read_to_string(&path)
+        .map_err(|error| ApplicationError::from_read_error(&path, error))?;
+    config_file_flags(&path, &content)
+}
+
+/// Turns `key = value` lines into flags: `ignore_case = true` gives `--ignore-case`, `false`
+/// leaves the flag out and any other value follows the flag, e.g. `skip_files = \.lock$`.
+/// `color` and `sort_matches` are written as `--color=auto`. Blank lines and `#` comments are
+/// skipped.
+fn config_file_flags(path: &str, content: &str) -> Result<Vec<String>, ApplicationError> {
+    let mut flags = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .filter(|(key, _)| !key.is_empty())
+            .ok_or_else(|| ApplicationError::InvalidFlag(format!("{}: {}", path, line)))?;
+
+        if UNSAFE_CONFIG_KEYS.contains(&key) {
+            return Err(ApplicationError::InvalidFlag(format!("{}: {}", path, key)));
+        }
+
+        let flag = format!("--{}", key.replace('_', "-"));
+
+        match value {
+            "true" => flags.push(flag),
+            "false" => {}
+            _ if matches!(key, "color" | "sort_matches") => {
+                flags.push(format!("{}={}", flag, value))
+            }
+            _ => flags.extend([flag, value.to_string()]),
+        }
+    }
+
+    Ok(flags)
 }
 
 impl Config {
     pub fn build(args: impl Iterator<Item = String>) -> Result<Config, ApplicationError> {
+        Config::build_with_env(args, |name| env::var(name).ok())
+    }
+
+    /// Builds the config with the defaults from `--config PATH` (or `GREPLITE_CONFIG_PATH`) and then the
+    /// flags in `GREPLITE_OPTS` (read through `lookup`) placed before the command-line arguments,
+    /// so explicit flags such as `--color=never` override them.
+    /// The variable may only hold flags; a pattern or file name in it is rejected.
+    pub fn build_with_env<F: Fn(&str) -> Option<String>>(
+        args: impl Iterator<Item = String>,
+        lookup: F,
+    ) -> Result<Config, ApplicationError> {
+        let mut args: Vec<String> = args.collect();
+        let program = (!args.is_empty()).then(|| args.remove(0));
+        let file_flags = read_config_file(&mut args, &lookup)?;
+        let defaults: Vec<String> = lookup("GREPLITE_OPTS")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+
+        if let Some(arg) = defaults
+            .iter()
+            .find(|arg| VALUE_FLAGS.contains(&arg.as_str()))
+        {
+            return Err(ApplicationError::InvalidFlag(format!(
+                "GREPLITE_OPTS: {} takes a value",
+                arg
+            )));
+        }
+
+        if let Some(arg) = defaults
+            .iter()
+            .find(|arg| !arg.starts_with('-') || arg.as_str() == "-")
+        {
+            return Err(ApplicationError::InvalidFlag(format!(
+                "GREPLITE_OPTS: {}",
+                arg
+            )));
+        }
+
+        Config::parse(
+            program
+                .into_iter()
+                .chain(file_flags)
+                .chain(defaults)
+                .chain(args),
+        )
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> Result<Config, ApplicationError> {
         let mut ignore_case = false;
         let mut show_line_numbers = false;
         let mut use_regex = false;
-        let mut enable_highlighting = false;
+        let mut color = ColorChoice::Never;
         let mut recursive_search = false;
+        let mut heading = false;
+        let mut trim = false;
+        let mut sort_by = None;
+        let mut range = None;
+        let mut skip_files = None;
+        let mut section = None;
+        let mut section_name = None;
+        let mut count_files = false;
+        let mut count_chars = false;
+        let mut match_histogram = false;
+        let mut verbose = false;
+        let mut quiet = false;
+        let mut json_array = false;
+        let mut pattern_file = None;
+        let mut multiline = false;
+        let mut show_byte_offset = false;
+        let mut global_numbers = false;
+        let mut align_numbers = false;
+        let mut relative_numbers = false;
+        let mut fixed_strings = false;
+        let mut tab_size = None;
+        let mut max_columns = None;
+        let mut preview_match = false;
+        let mut print_first_match = false;
+        let mut files_without_match = false;
+        let mut null_terminated = false;
+        let mut regex_size_limit = None;
+        let mut dfa_size_limit = None;
+        let mut timeout = None;
+        let mut suppress_errors = false;
+        let mut extract_group = None;
+        let mut extract_name = None;
+        let mut first_per_line = false;
+        let mut stdin_label = None;
+        let mut auto_recursive = false;
+        let mut follow_links = false;
+        let mut hidden = false;
+        let mut unrestricted = 0;
+        let mut binary_text_preview = false;
+        let mut preprocess_cmd = None;
+        let mut passthrough = false;
+        let mut path_style = None;
+        let mut min_match_len = None;
+        let mut fuzzy = None;
+        let mut max_count = None;
+        let mut name_only = false;
+        let mut comment_char = Some('#');
+        let mut field = None;
+        let mut delimiter = None;
+        let mut unique = false;
+        let mut dim_context = false;
+        let mut replace = None;
+        let mut in_place = false;
+        let mut in_place_backup = None;
+        let mut replace_dry_run = false;
+        let mut top = None;
+        let mut line_numbers_only = false;
+        let mut slurp = false;
+        let mut file_types = Vec::new();
+        let mut excluded_types = Vec::new();
+        let mut before_context = 0;
+        let mut after_context = 0;
+        let mut max_results = None;
+        let mut word_regexp = false;
+        let mut ascii_word = false;
+        let mut sort_matches = None;
+        let mut reverse = false;
+        let mut invert_match = false;
+        let mut context_first_only = false;
+        let mut trim_context_blank = false;
+        let mut indent_context = 0;
+        let mut regex_flags = None;
+        let mut no_unicode = false;
+        let mut match_separator = None;
+        let mut context_separator = None;
+        let mut output_separator = None;
+        let mut head_bytes = None;
+        let mut count = false;
+        let mut count_distinct = false;
+        let mut files_with_count = false;
+        let mut files_with_count_sorted = false;
+        let mut count_nonzero = false;
+        let mut exit_on_error = false;
+        let mut newer_than = None;
+        let mut older_than = None;
+        let mut progress = false;
+        let mut no_glob = false;
+        let mut name_pattern = None;
+        let mut inline_input = None;
+        let mut multi_match_only = false;
+        let mut highlight_style = HighlightStyle::Color;
         let mut query = String::new();
         let mut file_paths = Vec::new();
         let mut args_iter = args.skip(1);
@@ -26,17 +410,404 @@ impl Config {
         while let Some(arg) = args_iter.next() {
             match arg.as_str() {
                 "-h" | "--help" => return Err(ApplicationError::HelpRequested),
+                "--help-regex" => return Err(ApplicationError::RegexHelpRequested),
                 "-i" | "--ignore-case" => ignore_case = true,
                 "-n" | "--line-numbers" => show_line_numbers = true,
                 "-R" | "--recursive" => recursive_search = true,
+                "--auto-recursive" => auto_recursive = true,
+                "-S" | "--follow" => follow_links = true,
+                "--hidden" => hidden = true,
+                "-u" | "--unrestricted" => unrestricted += 1,
+                "-uu" => unrestricted += 2,
+                "-uuu" => unrestricted += 3,
+                "--binary-text-preview" => binary_text_preview = true,
                 "-r" | "--use-regex" => use_regex = true,
-                "-c" | "--color" => enable_highlighting = true,
+                "-F" | "--fixed-strings" => fixed_strings = true,
+                "-w" | "--word-regexp" => word_regexp = true,
+                "-v" | "--invert-match" => invert_match = true,
+                "--unicode-word" => ascii_word = false,
+                "--no-unicode-word" => ascii_word = true,
+                "-c" | "--color" => color = ColorChoice::On,
+                "-q" | "--quiet" => quiet = true,
+                "--json-array" => json_array = true,
+                "-s" | "--no-messages" => suppress_errors = true,
+                "-U" | "--multiline" => multiline = true,
+                "-b" | "--byte-offset" => show_byte_offset = true,
+                "--global-numbers" => global_numbers = true,
+                "--align-numbers" => align_numbers = true,
+                "--relative-numbers" => relative_numbers = true,
+                "-Z" | "--null" => null_terminated = true,
+                "--print-first-match" => print_first_match = true,
+                "-L" | "--files-without-match" => files_without_match = true,
+                "--passthrough" => passthrough = true,
+                "--first-only" => max_count = Some(1),
+                "--name-only" => name_only = true,
+                "--no-comments" => comment_char = None,
+                "--exit-on-error" => exit_on_error = true,
+                "--progress" => progress = true,
+                "--no-glob" => no_glob = true,
+                "--count" => count = true,
+                "--count-distinct" => count_distinct = true,
+                "--files-with-count" => files_with_count = true,
+                "--files-with-count=desc" => {
+                    files_with_count = true;
+                    files_with_count_sorted = true;
+                }
+                "--count-nonzero" => count_nonzero = true,
+                "--multi-match-only" => multi_match_only = true,
+                "--first-per-line" => first_per_line = true,
+                "--unique" => unique = true,
+                "--context-first-only" => context_first_only = true,
+                "--trim-context-blank" => trim_context_blank = true,
+                "--indent-context" => {
+                    indent_context = args_iter
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?;
+                }
+                "--reverse" => reverse = true,
+                "--dim-context" => dim_context = true,
+                "--in-place" => in_place = true,
+                "--replace-dry-run" => replace_dry_run = true,
+                "--line-numbers-only" => line_numbers_only = true,
+                "--slurp" => slurp = true,
+                "--path-absolute" => path_style = Some(PathStyle::Absolute),
+                "--heading" => heading = true,
+                "--trim" => trim = true,
+                "--count-files" => count_files = true,
+                "--count-chars" => count_chars = true,
+                "--match-histogram" => match_histogram = true,
+                "--verbose" => verbose = true,
+                "--range" => {
+                    range = Some(
+                        args_iter
+                            .next()
+                            .as_deref()
+                            .and_then(parse_range)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "-f" | "--file" => {
+                    pattern_file = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--comment-char" => {
+                    let value = args_iter.next().unwrap_or_default();
+                    let mut chars = value.chars();
+
+                    comment_char = match (chars.next(), chars.next()) {
+                        (Some(comment_char), None) => Some(comment_char),
+                        _ => return Err(ApplicationError::InvalidFlag(arg.to_string())),
+                    };
+                }
+                "--field" => {
+                    field = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .filter(|field| *field > 0)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--delimiter" => {
+                    let value = args_iter.next().unwrap_or_default();
+                    let mut chars = value.chars();
+
+                    delimiter = match (chars.next(), chars.next()) {
+                        (Some(delimiter), None) => Some(delimiter),
+                        _ => return Err(ApplicationError::InvalidFlag(arg.to_string())),
+                    };
+                }
+                "--tabsize" => {
+                    tab_size = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .filter(|size| *size > 0)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "-M" | "--max-columns" => {
+                    max_columns = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .filter(|columns| *columns > 0)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--preview-match" => preview_match = true,
+                "--type" | "--type-not" => {
+                    let name = args_iter
+                        .next()
+                        .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?;
+
+                    if extensions_for(&name).is_none() {
+                        return Err(ApplicationError::UnknownFileType(name));
+                    }
+
+                    if arg == "--type" {
+                        file_types.push(name);
+                    } else {
+                        excluded_types.push(name);
+                    }
+                }
+                "-A" | "--after-context" | "-B" | "--before-context" | "-C" | "--context" => {
+                    let lines = args_iter
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?;
+
+                    match arg.as_str() {
+                        "-A" | "--after-context" => after_context = lines,
+                        "-B" | "--before-context" => before_context = lines,
+                        _ => {
+                            before_context = lines;
+                            after_context = lines;
+                        }
+                    }
+                }
+                "--max-results" => {
+                    max_results = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .filter(|max_results| *max_results > 0)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--top" => {
+                    top = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .filter(|top| *top > 0)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--fuzzy" => {
+                    fuzzy = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--min-len" => {
+                    min_match_len = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--regex-size-limit" => {
+                    regex_size_limit = Some(
+                        args_iter
+                            .next()
+                            .as_deref()
+                            .and_then(parse_size)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--head-bytes" => {
+                    head_bytes = Some(
+                        args_iter
+                            .next()
+                            .as_deref()
+                            .and_then(parse_size)
+                            .filter(|head_bytes| *head_bytes > 0)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--dfa-size-limit" => {
+                    dfa_size_limit = Some(
+                        args_iter
+                            .next()
+                            .as_deref()
+                            .and_then(parse_size)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--timeout" => {
+                    timeout = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .map(Duration::from_millis)
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--input" => {
+                    inline_input = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--name-pattern" => {
+                    name_pattern = Some(
+                        args_iter
+                            .next()
+                            .filter(|pattern| !pattern.is_empty())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--newer-than" | "--older-than" => {
+                    let age = args_iter
+                        .next()
+                        .as_deref()
+                        .and_then(parse_duration)
+                        .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?;
+
+                    if arg == "--newer-than" {
+                        newer_than = Some(age);
+                    } else {
+                        older_than = Some(age);
+                    }
+                }
+                "--no-unicode" => no_unicode = true,
+                "--match-separator" => {
+                    match_separator = Some(
+                        args_iter
+                            .next()
+                            .filter(|separator| !separator.is_empty())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--context-separator" => {
+                    context_separator = Some(
+                        args_iter
+                            .next()
+                            .filter(|separator| !separator.is_empty())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--output-separator" => {
+                    output_separator = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--regex-flags" => {
+                    regex_flags = Some(
+                        args_iter
+                            .next()
+                            .filter(|flags| {
+                                !flags.is_empty() && flags.chars().all(|flag| "imsx".contains(flag))
+                            })
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--extract" => {
+                    extract_group = Some(
+                        args_iter
+                            .next()
+                            .and_then(|value| value.parse().ok())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--extract-name" => {
+                    extract_name = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--label" => {
+                    stdin_label = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--path-relative" => {
+                    path_style =
+                        Some(PathStyle::Relative(args_iter.next().ok_or_else(|| {
+                            ApplicationError::InvalidFlag(arg.to_string())
+                        })?));
+                }
+                "--replace" => {
+                    replace = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--in-place-backup" => {
+                    in_place = true;
+                    in_place_backup = Some(
+                        args_iter
+                            .next()
+                            .filter(|suffix| !suffix.is_empty())
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--pre" => {
+                    let command = args_iter
+                        .next()
+                        .filter(|command| !command.trim().is_empty())
+                        .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?;
+                    preprocess_cmd = Some(command);
+                }
+                "--skip-files" => {
+                    skip_files = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--section" => {
+                    section = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                "--section-name" => {
+                    section_name = Some(
+                        args_iter
+                            .next()
+                            .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?,
+                    );
+                }
+                _ if arg.starts_with("--color=") => {
+                    color = match &arg["--color=".len()..] {
+                        "always" => ColorChoice::Always,
+                        "auto" => ColorChoice::Auto,
+                        "never" => ColorChoice::Never,
+                        _ => return Err(ApplicationError::InvalidFlag(arg.to_string())),
+                    };
+                }
+                _ if arg.starts_with("--sort=") => {
+                    sort_by = Some(match &arg["--sort=".len()..] {
+                        "path" => SortBy::Path,
+                        "modified" => SortBy::Modified,
+                        "size" => SortBy::Size,
+                        _ => return Err(ApplicationError::InvalidFlag(arg.to_string())),
+                    });
+                }
+                _ if arg.starts_with("--highlight-style=") => {
+                    highlight_style = HighlightStyle::from_name(&arg["--highlight-style=".len()..])
+                        .ok_or_else(|| ApplicationError::InvalidFlag(arg.to_string()))?;
+                }
+                _ if arg.starts_with("--sort-matches=") => {
+                    sort_matches = Some(match &arg["--sort-matches=".len()..] {
+                        "text" => MatchOrder::Text,
+                        "numeric" => MatchOrder::Numeric,
+                        _ => return Err(ApplicationError::InvalidFlag(arg.to_string())),
+                    });
+                }
                 _ => {
-                    if arg.starts_with('-') {
+                    if arg.starts_with('-') && arg != "-" {
                         return Err(ApplicationError::InvalidFlag(arg.to_string()));
                     }
 
-                    if query.is_empty() {
+                    if query.is_empty() && pattern_file.is_none() {
                         query = arg.to_string();
                     } else {
                         file_paths.push(arg.to_string());
@@ -45,9 +816,262 @@ impl Config {
             }
         }
 
-        let read_from_stdin = file_paths.is_empty();
+        if inline_input.is_some() && !file_paths.is_empty() {
+            return Err(ApplicationError::InvalidFlag("--input".to_string()));
+        }
+
+        let read_from_stdin = file_paths.is_empty() && inline_input.is_none();
+
+        if fixed_strings && use_regex {
+            query = regex::escape(&query);
+        }
+
+        if multiline && !use_regex {
+            return Err(ApplicationError::InvalidFlag("--multiline".to_string()));
+        }
+
+        if regex_flags.is_some() && !use_regex {
+            return Err(ApplicationError::InvalidFlag("--regex-flags".to_string()));
+        }
+
+        if no_unicode && !use_regex {
+            return Err(ApplicationError::InvalidFlag("--no-unicode".to_string()));
+        }
+
+        if extract_group.is_some() && !use_regex {
+            return Err(ApplicationError::InvalidFlag("--extract".to_string()));
+        }
+
+        if extract_name.is_some() && !use_regex {
+            return Err(ApplicationError::InvalidFlag("--extract-name".to_string()));
+        }
+
+        if first_per_line && extract_group.is_none() && extract_name.is_none() {
+            return Err(ApplicationError::InvalidFlag(
+                "--first-per-line".to_string(),
+            ));
+        }
+
+        if ascii_word && !word_regexp {
+            return Err(ApplicationError::InvalidFlag(
+                "--no-unicode-word".to_string(),
+            ));
+        }
+
+        if dim_context && !passthrough && before_context == 0 && after_context == 0 {
+            return Err(ApplicationError::InvalidFlag("--dim-context".to_string()));
+        }
+
+        if context_first_only && before_context == 0 && after_context == 0 {
+            return Err(ApplicationError::InvalidFlag(
+                "--context-first-only".to_string(),
+            ));
+        }
+
+        if trim_context_blank && before_context == 0 && after_context == 0 {
+            return Err(ApplicationError::InvalidFlag(
+                "--trim-context-blank".to_string(),
+            ));
+        }
+
+        if indent_context > 0 && before_context == 0 && after_context == 0 {
+            return Err(ApplicationError::InvalidFlag(
+                "--indent-context".to_string(),
+            ));
+        }
+
+        match (&section, &section_name) {
+            (Some(_), None) => return Err(ApplicationError::InvalidFlag("--section".to_string())),
+            (None, Some(_)) => {
+                return Err(ApplicationError::InvalidFlag("--section-name".to_string()))
+            }
+            _ => {}
+        }
+
+        if count_nonzero && !count && !count_distinct {
+            return Err(ApplicationError::InvalidFlag("--count-nonzero".to_string()));
+        }
+
+        if align_numbers && !show_line_numbers {
+            return Err(ApplicationError::InvalidFlag("--align-numbers".to_string()));
+        }
+
+        if relative_numbers && (!show_line_numbers || (before_context == 0 && after_context == 0)) {
+            return Err(ApplicationError::InvalidFlag(
+                "--relative-numbers".to_string(),
+            ));
+        }
+
+        if delimiter.is_some() && field.is_none() {
+            return Err(ApplicationError::InvalidFlag("--delimiter".to_string()));
+        }
+
+        if preview_match && max_columns.is_none() {
+            return Err(ApplicationError::InvalidFlag("--preview-match".to_string()));
+        }
+
+        if in_place
+            && (replace.is_none()
+                || read_from_stdin
+                || inline_input.is_some()
+                || file_paths.iter().any(|path| path == "-"))
+        {
+            return Err(ApplicationError::InvalidFlag("--in-place".to_string()));
+        }
+
+        if replace_dry_run && replace.is_none() {
+            return Err(ApplicationError::InvalidFlag(
+                "--replace-dry-run".to_string(),
+            ));
+        }
+
+        let conflicts = [
+            (fuzzy.is_some(), "--fuzzy", use_regex, "--use-regex"),
+            (fuzzy.is_some(), "--fuzzy", word_regexp, "--word-regexp"),
+            (fuzzy.is_some(), "--fuzzy", pattern_file.is_some(), "--file"),
+            (slurp, "--slurp", multiline, "--multiline"),
+            (field.is_some(), "--field", multiline, "--multiline"),
+            (field.is_some(), "--field", slurp, "--slurp"),
+            (slurp, "--slurp", passthrough, "--passthrough"),
+            (quiet, "--quiet", print_first_match, "--print-first-match"),
+            (json_array, "--json-array", quiet, "--quiet"),
+            (json_array, "--json-array", count, "--count"),
+            (
+                json_array,
+                "--json-array",
+                count_distinct,
+                "--count-distinct",
+            ),
+            (
+                json_array,
+                "--json-array",
+                files_without_match,
+                "--files-without-match",
+            ),
+            (
+                json_array,
+                "--json-array",
+                print_first_match,
+                "--print-first-match",
+            ),
+            (
+                files_without_match,
+                "--files-without-match",
+                print_first_match,
+                "--print-first-match",
+            ),
+            (
+                files_without_match,
+                "--files-without-match",
+                count,
+                "--count",
+            ),
+            (
+                files_without_match,
+                "--files-without-match",
+                count_distinct,
+                "--count-distinct",
+            ),
+            (
+                files_with_count,
+                "--files-with-count",
+                files_without_match,
+                "--files-without-match",
+            ),
+            (
+                files_with_count,
+                "--files-with-count",
+                json_array,
+                "--json-array",
+            ),
+            (in_place, "--in-place", head_bytes.is_some(), "--head-bytes"),
+            (in_place, "--in-place", replace_dry_run, "--replace-dry-run"),
+            (invert_match, "--invert-match", slurp, "--slurp"),
+            (
+                invert_match,
+                "--invert-match",
+                multi_match_only,
+                "--multi-match-only",
+            ),
+            (slurp, "--slurp", multi_match_only, "--multi-match-only"),
+            (invert_match, "--invert-match", multiline, "--multiline"),
+            (
+                invert_match,
+                "--invert-match",
+                extract_group.is_some(),
+                "--extract",
+            ),
+            (
+                invert_match,
+                "--invert-match",
+                extract_name.is_some(),
+                "--extract-name",
+            ),
+            (
+                sort_matches.is_some(),
+                "--sort-matches",
+                before_context > 0 || after_context > 0,
+                "--context",
+            ),
+            (
+                reverse,
+                "--reverse",
+                before_context > 0 || after_context > 0,
+                "--context",
+            ),
+            (
+                reverse,
+                "--reverse",
+                sort_matches.is_some(),
+                "--sort-matches",
+            ),
+            (
+                passthrough,
+                "--passthrough",
+                max_count.is_some(),
+                "--first-only",
+            ),
+            (
+                passthrough,
+                "--passthrough",
+                extract_group.is_some(),
+                "--extract",
+            ),
+            (
+                passthrough,
+                "--passthrough",
+                extract_name.is_some(),
+                "--extract-name",
+            ),
+            (passthrough, "--passthrough", name_only, "--name-only"),
+            (
+                extract_group.is_some(),
+                "--extract",
+                extract_name.is_some(),
+                "--extract-name",
+            ),
+            (
+                name_only,
+                "--name-only",
+                extract_group.is_some(),
+                "--extract",
+            ),
+            (
+                name_only,
+                "--name-only",
+                extract_name.is_some(),
+                "--extract-name",
+            ),
+        ];
+
+        if let Some((_, first, _, second)) = conflicts.iter().find(|(a, _, b, _)| *a && *b) {
+            return Err(ApplicationError::ConflictingFlags(
+                first.to_string(),
+                second.to_string(),
+            ));
+        }
 
-        if query.is_empty() {
+        if query.is_empty() && pattern_file.is_none() {
             return Err(ApplicationError::NotEnoughArguments);
         }
 
@@ -57,11 +1081,170 @@ impl Config {
             ignore_case,
             show_line_numbers,
             use_regex,
-            enable_highlighting,
+            enable_highlighting: color != ColorChoice::Never,
             read_from_stdin,
             recursive_search,
+            heading,
+            trim,
+            sort_by,
+            range,
+            skip_files,
+            section,
+            section_name,
+            count_files,
+            count_chars,
+            match_histogram,
+            verbose,
+            quiet,
+            json_array,
+            pattern_file,
+            multiline,
+            show_byte_offset,
+            global_numbers,
+            align_numbers,
+            relative_numbers,
+            fixed_strings,
+            tab_size,
+            max_columns,
+            preview_match,
+            print_first_match,
+            files_without_match,
+            null_terminated,
+            regex_size_limit,
+            dfa_size_limit,
+            timeout,
+            suppress_errors,
+            extract_group,
+            extract_name,
+            first_per_line,
+            stdin_label,
+            auto_recursive,
+            follow_links,
+            hidden: hidden || unrestricted >= 2,
+            binary_as_text: binary_text_preview || unrestricted >= 3,
+            binary_text_preview,
+            preprocess_cmd,
+            color,
+            passthrough,
+            path_style,
+            min_match_len,
+            fuzzy,
+            max_count,
+            name_only,
+            comment_char,
+            field,
+            delimiter,
+            unique,
+            dim_context,
+            replace,
+            in_place,
+            in_place_backup,
+            replace_dry_run,
+            top,
+            line_numbers_only,
+            slurp,
+            file_types,
+            excluded_types,
+            before_context,
+            after_context,
+            max_results,
+            word_regexp,
+            ascii_word,
+            sort_matches,
+            reverse,
+            invert_match,
+            context_first_only,
+            trim_context_blank,
+            indent_context,
+            regex_flags,
+            no_unicode,
+            match_separator,
+            context_separator,
+            output_separator,
+            head_bytes,
+            count,
+            count_distinct,
+            files_with_count,
+            files_with_count_sorted,
+            count_nonzero,
+            exit_on_error,
+            newer_than,
+            older_than,
+            progress,
+            no_glob,
+            name_pattern,
+            inline_input,
+            multi_match_only,
+            highlight_style,
         })
     }
+
+    /// Decides whether to highlight output, honoring `NO_COLOR`, `CLICOLOR_FORCE` and `CLICOLOR`
+    /// (in that order of precedence) unless `--color=always` or `--color=never` was given.
+    /// With `--color=auto`, output that isn't a terminal is only highlighted with `CLICOLOR_FORCE`.
+    pub fn resolve_color<F: Fn(&str) -> Option<String>>(&mut self, is_terminal: bool, lookup: F) {
+        let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+
+        self.enable_highlighting = match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            color @ (ColorChoice::On | ColorChoice::Auto) => {
+                if var("NO_COLOR").is_some() {
+                    false
+                } else if var("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+                    true
+                } else if color == ColorChoice::Auto && !is_terminal {
+                    false
+                } else {
+                    var("CLICOLOR").as_deref() != Some("0")
+                }
+            }
+        };
+    }
+}
+
+fn parse_range(value: &str) -> Option<(usize, usize)> {
+    let (start, end) = value.split_once(':')?;
+    let start = start.parse().ok()?;
+    let end = end.parse().ok()?;
+
+    if start == 0 || start > end {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+fn parse_size(value: &str) -> Option<usize> {
+    let value = value.to_ascii_uppercase();
+    let value = value.strip_suffix('B').unwrap_or(&value);
+
+    let (digits, multiplier) = match value.chars().last()? {
+        'K' => (&value[..value.len() - 1], 1024),
+        'M' => (&value[..value.len() - 1], 1024 * 1024),
+        'G' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    value[..value.len() - 1]
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
 }
 
 #[cfg(test)]
@@ -104,6 +1287,7 @@ mod tests {
         assert!(config.show_line_numbers);
         assert!(config.use_regex);
         assert!(config.enable_highlighting);
+        assert_eq!(config.color, ColorChoice::On);
     }
 
     #[test]
@@ -128,53 +1312,2254 @@ mod tests {
     }
 
     #[test]
-    fn test_invalid_flag() {
+    fn test_config_with_color_when() {
+        for (flag, expected) in [
+            ("--color=always", ColorChoice::Always),
+            ("--color=auto", ColorChoice::Auto),
+            ("--color=never", ColorChoice::Never),
+        ] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "rust".to_string(),
+                "poem.txt".to_string(),
+            ];
+
+            let config = Config::build(args.into_iter()).unwrap();
+            assert_eq!(config.color, expected);
+            assert_eq!(config.enable_highlighting, expected != ColorChoice::Never);
+        }
+
+        let args = vec!["minigrep".to_string(), "--color=rainbow".to_string()];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref s)) if s == "--color=rainbow")
+        );
+    }
+
+    fn resolved_color(color: ColorChoice, vars: &[(&str, &str)]) -> bool {
+        resolved_color_on(true, color, vars)
+    }
+
+    fn resolved_color_on(is_terminal: bool, color: ColorChoice, vars: &[(&str, &str)]) -> bool {
+        let mut config = Config {
+            color,
+            ..Default::default()
+        };
+        config.resolve_color(is_terminal, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        });
+        config.enable_highlighting
+    }
+
+    #[test]
+    fn test_build_with_env_applies_default_flags() {
         let args = vec![
             "minigrep".to_string(),
+            "--color=never".to_string(),
             "rust".to_string(),
-            "--unknown".to_string(),
-            "poem.txt".to_string(),
         ];
+        let lookup =
+            |name: &str| (name == "GREPLITE_OPTS").then(|| "-n  -i --color=always".to_string());
 
-        let result = Config::build(args.into_iter());
+        let config = Config::build_with_env(args.into_iter(), lookup).unwrap();
 
-        assert!(
-            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--unknown"),
-            "Expected InvalidFlag error with '--unknown', but got {:?}",
-            result
-        );
+        assert!(config.show_line_numbers);
+        assert!(config.ignore_case);
+        assert_eq!(config.color, ColorChoice::Never);
+        assert_eq!(config.query, "rust");
+        assert!(config.file_paths.is_empty());
     }
 
     #[test]
-    fn test_not_enough_arguments() {
-        let args = vec!["minigrep".to_string()];
-        let result = Config::build(args.into_iter());
-        assert!(
-            matches!(result, Err(ApplicationError::NotEnoughArguments)),
-            "Expected NotEnoughArguments error, but got {:?}",
-            result
-        );
+    fn test_build_with_config_file() {
+        let path = env::temp_dir().join(format!("greplite-rc-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# project defaults\nignore_case = true\nheading=false\ncolor=auto\nskip_files=\\.lock$\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--config".to_string(),
+            path.clone(),
+            "rust".to_string(),
+        ];
+        let config = Config::build_with_env(args.into_iter(), |_| None).unwrap();
+
+        assert!(config.ignore_case);
+        assert!(!config.heading);
+        assert_eq!(config.color, ColorChoice::Auto);
+        assert_eq!(config.skip_files, Some(r"\.lock$".to_string()));
+        assert_eq!(config.query, "rust");
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--color=never".to_string(),
+            "--skip-files".to_string(),
+            r"\.tmp$".to_string(),
+            "rust".to_string(),
+            "--config".to_string(),
+            path.clone(),
+        ];
+        let config = Config::build_with_env(args.into_iter(), |_| None).unwrap();
+
+        assert_eq!(config.color, ColorChoice::Never);
+        assert_eq!(config.skip_files, Some(r"\.tmp$".to_string()));
+
+        fs::remove_file(&path).unwrap();
     }
 
     #[test]
-    fn test_missing_query_or_file_paths() {
-        let args = vec!["minigrep".to_string(), "-i".to_string()];
-        let result = Config::build(args.into_iter());
+    fn test_build_with_config_file_errors() {
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "--config".to_string(),
+        ];
+        let result = Config::build_with_env(args.into_iter(), |_| None);
         assert!(
-            matches!(result, Err(ApplicationError::NotEnoughArguments)),
-            "Expected NotEnoughArguments error, but got {:?}",
-            result
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--config")
         );
-    }
 
-    #[test]
-    fn test_help_requested() {
-        let args = vec!["minigrep".to_string(), "--help".to_string()];
-        let result = Config::build(args.into_iter());
+        let args = vec![
+            "minigrep".to_string(),
+            "--config".to_string(),
+            "missing.grepliterc".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build_with_env(args.into_iter(), |_| None);
         assert!(
-            matches!(result, Err(ApplicationError::HelpRequested)),
-            "Expected HelpRequested error, but got {:?}",
-            result
+            matches!(result, Err(ApplicationError::FileNotFound(ref path)) if path == "missing.grepliterc")
         );
+
+        assert!(matches!(
+            config_file_flags(".grepliterc", "ignore_case"),
+            Err(ApplicationError::InvalidFlag(ref line)) if line == ".grepliterc: ignore_case"
+        ));
+    }
+
+    #[test]
+    fn test_build_with_config_path_variable() {
+        let path = env::temp_dir().join(format!("greplite-rc-env-{}", std::process::id()));
+        fs::write(&path, "ignore_case = true\n").unwrap();
+        let path = path.to_str().unwrap().to_string();
+        let args = || vec!["minigrep".to_string(), "rust".to_string()].into_iter();
+
+        let config = Config::build_with_env(args(), |name| {
+            (name == "GREPLITE_CONFIG_PATH").then(|| path.clone())
+        })
+        .unwrap();
+        assert!(config.ignore_case);
+
+        let config = Config::build_with_env(args(), |_| None).unwrap();
+        assert!(!config.ignore_case);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_config_file_rejects_side_effects() {
+        for content in [
+            "pre = touch PWNED",
+            "replace = x",
+            "in_place = true",
+            "in_place_backup = .bak",
+        ] {
+            let key = content.split(' ').next().unwrap();
+
+            assert!(
+                matches!(
+                    config_file_flags("rc", content),
+                    Err(ApplicationError::InvalidFlag(ref flag)) if *flag == format!("rc: {}", key)
+                ),
+                "Expected {:?} to be rejected",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_with_env_rejects_non_flags() {
+        for opts in ["-n secret", "-i -"] {
+            let args = vec!["minigrep".to_string(), "rust".to_string()];
+
+            let result = Config::build_with_env(args.into_iter(), |name| {
+                (name == "GREPLITE_OPTS").then(|| opts.to_string())
+            });
+
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag.starts_with("GREPLITE_OPTS: ")),
+                "Expected GREPLITE_OPTS to be rejected for {:?}",
+                opts
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_with_env_rejects_value_flags() {
+        for opts in ["--label", "-n --replace", "--input", "-A 2"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "rust".to_string(),
+                "poem.txt".to_string(),
+            ];
+
+            let result = Config::build_with_env(args.into_iter(), |name| {
+                (name == "GREPLITE_OPTS").then(|| opts.to_string())
+            });
+
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag.ends_with(" takes a value")),
+                "Expected GREPLITE_OPTS to be rejected for {:?}",
+                opts
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_flags_match_parser() {
+        // Every match arm in `Config::parse` that reads `args_iter` takes a value, so listing
+        // those arms keeps `VALUE_FLAGS` from drifting when a flag is added.
+        let source = include_str!("config.rs");
+        let start = source
+            .find("while let Some(arg) = args_iter.next()")
+            .unwrap();
+        let parser = &source[start..];
+        let parser = &parser[..parser.find("\n        }\n").unwrap()];
+        let arm = regex::Regex::new(r#"(?m)^ {16}((?:"-[^"]*"(?: \| )?)+) =>"#).unwrap();
+        let arms: Vec<_> = arm.captures_iter(parser).collect();
+
+        let mut parsed = vec!["--config"];
+        for (index, captures) in arms.iter().enumerate() {
+            let end = arms
+                .get(index + 1)
+                .map_or(parser.len(), |next| next.get(0).unwrap().start());
+
+            if parser[captures.get(0).unwrap().end()..end].contains("args_iter") {
+                parsed.extend(captures[1].split(" | ").map(|flag| flag.trim_matches('"')));
+            }
+        }
+
+        let mut listed = VALUE_FLAGS.to_vec();
+        listed.sort_unstable();
+        parsed.sort_unstable();
+        assert!(parsed.len() > 1);
+        assert_eq!(listed, parsed);
+    }
+
+    #[test]
+    fn test_resolve_color_env() {
+        for color in [ColorChoice::On, ColorChoice::Auto] {
+            assert!(resolved_color(color, &[]));
+            assert!(!resolved_color(color, &[("NO_COLOR", "1")]));
+            assert!(resolved_color(color, &[("NO_COLOR", "")]));
+            assert!(!resolved_color(color, &[("CLICOLOR", "0")]));
+            assert!(resolved_color(color, &[("CLICOLOR", "1")]));
+            assert!(resolved_color(
+                color,
+                &[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")]
+            ));
+            assert!(!resolved_color(
+                color,
+                &[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "0")]
+            ));
+            assert!(!resolved_color(
+                color,
+                &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resolve_color_on_ignores_terminal() {
+        assert!(resolved_color_on(false, ColorChoice::On, &[]));
+        assert!(!resolved_color_on(
+            false,
+            ColorChoice::On,
+            &[("NO_COLOR", "1")]
+        ));
+    }
+
+    #[test]
+    fn test_resolve_color_auto_requires_terminal() {
+        assert!(resolved_color_on(true, ColorChoice::Auto, &[]));
+        assert!(!resolved_color_on(false, ColorChoice::Auto, &[]));
+        assert!(!resolved_color_on(
+            false,
+            ColorChoice::Auto,
+            &[("CLICOLOR", "1")]
+        ));
+        assert!(resolved_color_on(
+            false,
+            ColorChoice::Auto,
+            &[("CLICOLOR_FORCE", "1")]
+        ));
+        assert!(resolved_color_on(false, ColorChoice::Always, &[]));
+    }
+
+    #[test]
+    fn test_resolve_color_explicit_choice_ignores_env() {
+        let vars = [("NO_COLOR", "1"), ("CLICOLOR", "0")];
+        assert!(resolved_color(ColorChoice::Always, &vars));
+        assert!(!resolved_color(
+            ColorChoice::Never,
+            &[("CLICOLOR_FORCE", "1")]
+        ));
+    }
+
+    #[test]
+    fn test_config_with_heading() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--heading".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.heading);
+    }
+
+    #[test]
+    fn test_config_with_trim() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--trim".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.trim);
+    }
+
+    #[test]
+    fn test_config_with_quiet() {
+        for flag in ["-q", "--quiet"] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "rust".to_string(),
+                "poem.txt".to_string(),
+            ];
+
+            let config = Config::build(args.into_iter()).unwrap();
+            assert!(config.quiet);
+        }
+    }
+
+    #[test]
+    fn test_config_with_pattern_file() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-f".to_string(),
+            "patterns.txt".to_string(),
+            "poem.txt".to_string(),
+            "notes.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.pattern_file, Some("patterns.txt".to_string()));
+        assert_eq!(config.comment_char, Some('#'));
+        assert!(config.query.is_empty());
+        assert_eq!(
+            config.file_paths,
+            vec!["poem.txt".to_string(), "notes.txt".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_config_with_multiline() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "-U".to_string(),
+            "a.*b".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.multiline);
+    }
+
+    #[test]
+    fn test_config_multiline_requires_regex() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--multiline".to_string(),
+            "rust".to_string(),
+        ];
+
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--multiline"),
+            "Expected InvalidFlag error with '--multiline', but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_config_with_align_numbers() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-n".to_string(),
+            "--align-numbers".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.align_numbers);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--align-numbers".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--align-numbers")
+        );
+    }
+
+    #[test]
+    fn test_config_with_relative_numbers() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-n".to_string(),
+            "-C".to_string(),
+            "2".to_string(),
+            "--relative-numbers".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.relative_numbers);
+
+        for args in [
+            vec!["minigrep", "-n", "--relative-numbers", "rust"],
+            vec!["minigrep", "-C", "2", "--relative-numbers", "rust"],
+        ] {
+            let result = Config::build(args.into_iter().map(String::from));
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--relative-numbers")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_global_numbers() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--global-numbers".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.global_numbers);
+    }
+
+    #[test]
+    fn test_config_with_byte_offset() {
+        for flag in ["-b", "--byte-offset"] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "rust".to_string(),
+                "poem.txt".to_string(),
+            ];
+
+            let config = Config::build(args.into_iter()).unwrap();
+            assert!(config.show_byte_offset);
+        }
+    }
+
+    #[test]
+    fn test_config_fixed_strings_with_regex() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-F".to_string(),
+            "-r".to_string(),
+            "a.b(c)".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.fixed_strings);
+        assert!(config.use_regex);
+        assert_eq!(config.query, r"a\.b\(c\)");
+
+        let regex = crate::regex::compile_regex(&config.query, &config)
+            .unwrap()
+            .unwrap();
+        assert!(regex.is_match("x a.b(c) y"));
+        assert!(!regex.is_match("x axbc y"));
+    }
+
+    #[test]
+    fn test_config_fixed_strings_without_regex() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--fixed-strings".to_string(),
+            "a.b".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.fixed_strings);
+        assert!(!config.use_regex);
+        assert_eq!(config.query, "a.b");
+    }
+
+    #[test]
+    fn test_config_with_max_columns() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-M".to_string(),
+            "80".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.max_columns, Some(80));
+
+        for value in ["0", "wide"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--max-columns".to_string(),
+                value.to_string(),
+                "rust".to_string(),
+            ];
+
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--max-columns")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_preview_match() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-M".to_string(),
+            "80".to_string(),
+            "--preview-match".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.preview_match);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--preview-match".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--preview-match")
+        );
+    }
+
+    #[test]
+    fn test_config_with_tabsize() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--tabsize".to_string(),
+            "4".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.tab_size, Some(4));
+
+        for value in ["0", "four"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--tabsize".to_string(),
+                value.to_string(),
+                "rust".to_string(),
+            ];
+
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--tabsize")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_min_len() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--min-len".to_string(),
+            "4".to_string(),
+            r"\w+".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.min_match_len, Some(4));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--min-len".to_string(),
+            "four".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--min-len")
+        );
+    }
+
+    #[test]
+    fn test_config_with_print_first_match() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--print-first-match".to_string(),
+            "-Z".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.print_first_match);
+        assert!(config.null_terminated);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("10K"), Some(10 * 1024));
+        assert_eq!(parse_size("2mb"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("ten"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(
+            parse_duration("24h"),
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("7d"),
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2w"),
+            Some(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+        assert_eq!(parse_duration("24"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_config_with_file_age() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--newer-than".to_string(),
+            "7d".to_string(),
+            "--older-than".to_string(),
+            "1h".to_string(),
+            "-R".to_string(),
+            "error".to_string(),
+            "logs".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(
+            config.newer_than,
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(config.older_than, Some(Duration::from_secs(60 * 60)));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--newer-than".to_string(),
+            "yesterday".to_string(),
+            "error".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--newer-than")
+        );
+    }
+
+    #[test]
+    fn test_config_with_regex_size_limits() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--regex-size-limit".to_string(),
+            "1M".to_string(),
+            "--dfa-size-limit".to_string(),
+            "512K".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.regex_size_limit, Some(1024 * 1024));
+        assert_eq!(config.dfa_size_limit, Some(512 * 1024));
+    }
+
+    #[test]
+    fn test_config_with_timeout() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--timeout".to_string(),
+            "250".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.timeout, Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn test_config_with_no_messages() {
+        for flag in ["-s", "--no-messages"] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "rust".to_string(),
+                "src".to_string(),
+            ];
+
+            let config = Config::build(args.into_iter()).unwrap();
+            assert!(config.suppress_errors);
+        }
+    }
+
+    #[test]
+    fn test_config_with_extract() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--extract".to_string(),
+            "1".to_string(),
+            r"user=(\w+)".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.extract_group, Some(1));
+    }
+
+    #[test]
+    fn test_config_extract_requires_regex() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--extract".to_string(),
+            "1".to_string(),
+            "user".to_string(),
+        ];
+
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--extract"),
+            "Expected InvalidFlag error with '--extract', but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_config_with_extract_name() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--extract-name".to_string(),
+            "user".to_string(),
+            r"user=(?P<user>\w+)".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.extract_name, Some("user".to_string()));
+    }
+
+    #[test]
+    fn test_config_with_first_per_line() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--extract".to_string(),
+            "1".to_string(),
+            "--first-per-line".to_string(),
+            r"id=(\d+)".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.first_per_line);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--first-per-line".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--first-per-line")
+        );
+    }
+
+    #[test]
+    fn test_config_extract_name_requires_regex() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--extract-name".to_string(),
+            "user".to_string(),
+            "user".to_string(),
+        ];
+
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--extract-name"),
+            "Expected InvalidFlag error with '--extract-name', but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_config_with_label() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--label".to_string(),
+            "myservice".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.stdin_label, Some("myservice".to_string()));
+        assert!(config.read_from_stdin);
+    }
+
+    #[test]
+    fn test_config_with_auto_recursive() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--auto-recursive".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.auto_recursive);
+        assert!(!config.recursive_search);
+    }
+
+    #[test]
+    fn test_config_with_hidden() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--hidden".to_string(),
+            "-R".to_string(),
+            "rust".to_string(),
+            ".".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.hidden);
+    }
+
+    #[test]
+    fn test_config_with_binary_text_preview() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--binary-text-preview".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.binary_text_preview);
+        assert!(config.binary_as_text);
+    }
+
+    #[test]
+    fn test_config_with_unrestricted_levels() {
+        for (flags, hidden, binary_as_text) in [
+            (vec!["-u"], false, false),
+            (vec!["-uu"], true, false),
+            (vec!["-u", "--unrestricted"], true, false),
+            (vec!["-uuu"], true, true),
+        ] {
+            let args = ["minigrep"]
+                .into_iter()
+                .chain(flags)
+                .chain(["rust"])
+                .map(String::from);
+
+            let config = Config::build(args).unwrap();
+            assert_eq!(config.hidden, hidden);
+            assert_eq!(config.binary_as_text, binary_as_text);
+        }
+    }
+
+    #[test]
+    fn test_config_with_follow() {
+        for flag in ["-S", "--follow"] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "rust".to_string(),
+                "src".to_string(),
+            ];
+
+            let config = Config::build(args.into_iter()).unwrap();
+            assert!(config.follow_links);
+        }
+    }
+
+    #[test]
+    fn test_config_with_pre() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--pre".to_string(),
+            "zstd -dc".to_string(),
+            "rust".to_string(),
+            "log.zst".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.preprocess_cmd, Some("zstd -dc".to_string()));
+        assert_eq!(config.file_paths, vec!["log.zst".to_string()]);
+
+        let args = vec!["minigrep".to_string(), "--pre".to_string(), " ".to_string()];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(result, Err(ApplicationError::InvalidFlag(ref s)) if s == "--pre"));
+    }
+
+    #[test]
+    fn test_config_with_passthrough() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--passthrough".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.passthrough);
+    }
+
+    #[test]
+    fn test_config_with_path_style() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--path-absolute".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.path_style, Some(PathStyle::Absolute));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--path-relative".to_string(),
+            "/home/user".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(
+            config.path_style,
+            Some(PathStyle::Relative("/home/user".to_string()))
+        );
+        assert_eq!(config.file_paths, vec!["src".to_string()]);
+    }
+
+    #[test]
+    fn test_config_with_first_only() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--first-only".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.max_count, Some(1));
+    }
+
+    #[test]
+    fn test_config_with_name_only() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--name-only".to_string(),
+            "-R".to_string(),
+            "main".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.name_only);
+        assert!(config.recursive_search);
+    }
+
+    #[test]
+    fn test_config_with_word_regexp() {
+        let args = vec!["minigrep".to_string(), "-w".to_string(), "café".to_string()];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.word_regexp);
+        assert!(!config.ascii_word);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "-w".to_string(),
+            "--no-unicode-word".to_string(),
+            "café".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.ascii_word);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--no-unicode-word".to_string(),
+            "café".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--no-unicode-word")
+        );
+    }
+
+    #[test]
+    fn test_config_with_field() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--field".to_string(),
+            "3".to_string(),
+            "--delimiter".to_string(),
+            ";".to_string(),
+            "error".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.field, Some(3));
+        assert_eq!(config.delimiter, Some(';'));
+
+        for args in [
+            vec!["minigrep", "--field", "0", "error"],
+            vec!["minigrep", "--field", "third", "error"],
+        ] {
+            let result = Config::build(args.into_iter().map(String::from));
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--field")
+            );
+        }
+
+        for args in [
+            vec!["minigrep", "--delimiter", ",", "error"],
+            vec!["minigrep", "--field", "1", "--delimiter", "::", "error"],
+        ] {
+            let result = Config::build(args.into_iter().map(String::from));
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--delimiter")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_comment_char() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--comment-char".to_string(),
+            ";".to_string(),
+            "-f".to_string(),
+            "patterns.txt".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.comment_char, Some(';'));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--no-comments".to_string(),
+            "-f".to_string(),
+            "patterns.txt".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.comment_char, None);
+
+        for value in ["", "//"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--comment-char".to_string(),
+                value.to_string(),
+            ];
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--comment-char")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_unique() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--unique".to_string(),
+            "error".to_string(),
+            "app.log".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.unique);
+    }
+
+    #[test]
+    fn test_config_with_dim_context() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-c".to_string(),
+            "--passthrough".to_string(),
+            "--dim-context".to_string(),
+            "rust".to_string(),
+            "main.rs".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.dim_context);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--dim-context".to_string(),
+            "rust".to_string(),
+            "main.rs".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--dim-context")
+        );
+    }
+
+    #[test]
+    fn test_config_with_replace_in_place() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--replace".to_string(),
+            "bar".to_string(),
+            "--in-place-backup".to_string(),
+            ".bak".to_string(),
+            "foo".to_string(),
+            "file.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.replace, Some("bar".to_string()));
+        assert!(config.in_place);
+        assert_eq!(config.in_place_backup, Some(".bak".to_string()));
+    }
+
+    #[test]
+    fn test_config_with_replace_dry_run() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--replace".to_string(),
+            "bar".to_string(),
+            "--replace-dry-run".to_string(),
+            "foo".to_string(),
+            "file.txt".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.replace_dry_run);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--replace-dry-run".to_string(),
+            "foo".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--replace-dry-run")
+        );
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--replace".to_string(),
+            "bar".to_string(),
+            "--in-place".to_string(),
+            "--replace-dry-run".to_string(),
+            "foo".to_string(),
+            "file.txt".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref first, ref second))
+                if first == "--in-place" && second == "--replace-dry-run"
+        ));
+    }
+
+    #[test]
+    fn test_config_in_place_rejected() {
+        for args in [
+            vec!["--in-place", "foo", "file.txt"],
+            vec!["--replace", "bar", "--in-place", "foo"],
+            vec!["--replace", "bar", "--in-place", "foo", "file.txt", "-"],
+        ] {
+            let args = ["minigrep"].into_iter().chain(args).map(str::to_string);
+
+            let result = Config::build(args);
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--in-place"),
+                "Expected InvalidFlag error with '--in-place', but got {:?}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_top() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--top".to_string(),
+            "5".to_string(),
+            "error".to_string(),
+            "logs".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.top, Some(5));
+
+        for value in ["0", "five"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--top".to_string(),
+                value.to_string(),
+            ];
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--top")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_line_numbers_only() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--line-numbers-only".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.line_numbers_only);
+    }
+
+    #[test]
+    fn test_config_with_slurp() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--slurp".to_string(),
+            "begin.*end".to_string(),
+            "data.json".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.slurp);
+    }
+
+    #[test]
+    fn test_config_with_file_types() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--type".to_string(),
+            "rust".to_string(),
+            "--type".to_string(),
+            "toml".to_string(),
+            "--type-not".to_string(),
+            "md".to_string(),
+            "fn".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(
+            config.file_types,
+            vec!["rust".to_string(), "toml".to_string()]
+        );
+        assert_eq!(config.excluded_types, vec!["md".to_string()]);
+    }
+
+    #[test]
+    fn test_config_with_unknown_file_type() {
+        for flag in ["--type", "--type-not"] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "cobol".to_string(),
+                "fn".to_string(),
+            ];
+
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::UnknownFileType(ref name)) if name == "cobol"),
+                "Expected UnknownFileType error, but got {:?}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_context() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-C".to_string(),
+            "2".to_string(),
+            "-A".to_string(),
+            "4".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.before_context, 2);
+        assert_eq!(config.after_context, 4);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--before-context".to_string(),
+            "1".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.before_context, 1);
+        assert_eq!(config.after_context, 0);
+
+        let args = vec!["minigrep".to_string(), "-C".to_string(), "x".to_string()];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "-C"));
+    }
+
+    #[test]
+    fn test_config_with_max_results() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--max-results".to_string(),
+            "10".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.max_results, Some(10));
+
+        for value in ["0", "ten"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--max-results".to_string(),
+                value.to_string(),
+            ];
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--max-results")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_count_files() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--count-files".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.count_files);
+    }
+
+    #[test]
+    fn test_config_with_count_chars() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--count-chars".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.count_chars);
+    }
+
+    #[test]
+    fn test_config_with_verbose() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--verbose".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.verbose);
+    }
+
+    #[test]
+    fn test_config_with_match_histogram() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--match-histogram".to_string(),
+            "rust".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.match_histogram);
+    }
+
+    #[test]
+    fn test_config_with_sort() {
+        for (flag, expected) in [
+            ("--sort=path", SortBy::Path),
+            ("--sort=modified", SortBy::Modified),
+            ("--sort=size", SortBy::Size),
+        ] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                "rust".to_string(),
+                "src".to_string(),
+            ];
+
+            let config = Config::build(args.into_iter()).unwrap();
+            assert_eq!(config.sort_by, Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_config_with_highlight_style() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--color=always".to_string(),
+            "--highlight-style=bold-underline".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.highlight_style, HighlightStyle::BoldUnderline);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--highlight-style=blink".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--highlight-style=blink")
+        );
+    }
+
+    #[test]
+    fn test_config_with_multi_match_only() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--multi-match-only".to_string(),
+            r"\d+".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.multi_match_only);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--multi-match-only".to_string(),
+            "-v".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref a, ref b)) if a == "--invert-match" && b == "--multi-match-only"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_inline_input() {
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "--input".to_string(),
+            "rust is fast".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.inline_input.as_deref(), Some("rust is fast"));
+        assert!(!config.read_from_stdin);
+        assert!(config.file_paths.is_empty());
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--input".to_string(),
+            "rust is fast".to_string(),
+            "rust".to_string(),
+            "file.txt".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--input")
+        );
+    }
+
+    #[test]
+    fn test_config_with_name_pattern() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--name-pattern".to_string(),
+            "*.conf".to_string(),
+            "-R".to_string(),
+            "listen".to_string(),
+            "/etc".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.name_pattern.as_deref(), Some("*.conf"));
+        assert_eq!(config.query, "listen");
+    }
+
+    #[test]
+    fn test_config_with_progress() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--progress".to_string(),
+            "-R".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.progress);
+    }
+
+    #[test]
+    fn test_config_with_no_glob() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--no-glob".to_string(),
+            "rust".to_string(),
+            "*.txt".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.no_glob);
+        assert_eq!(config.file_paths, vec!["*.txt".to_string()]);
+    }
+
+    #[test]
+    fn test_config_with_exit_on_error() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--exit-on-error".to_string(),
+            "-R".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.exit_on_error);
+    }
+
+    #[test]
+    fn test_config_with_count() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--count".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.count);
+        assert!(!config.count_distinct);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--count-distinct".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.count_distinct);
+    }
+
+    #[test]
+    fn test_config_with_files_without_match() {
+        for flag in ["-L", "--files-without-match"] {
+            let args = vec!["minigrep".to_string(), flag.to_string(), "rust".to_string()];
+            let config = Config::build(args.into_iter()).unwrap();
+            assert!(config.files_without_match);
+        }
+
+        let args = vec![
+            "minigrep".to_string(),
+            "-L".to_string(),
+            "--count".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref first, ref second))
+                if first == "--files-without-match" && second == "--count"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_json_array() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--json-array".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.json_array);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--json-array".to_string(),
+            "--count".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref first, ref second))
+                if first == "--json-array" && second == "--count"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_files_with_count() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--files-with-count".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.files_with_count);
+        assert!(!config.files_with_count_sorted);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--files-with-count=desc".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.files_with_count);
+        assert!(config.files_with_count_sorted);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--files-with-count".to_string(),
+            "-L".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref a, ref b))
+                if a == "--files-with-count" && b == "--files-without-match"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_count_nonzero() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--count".to_string(),
+            "--count-nonzero".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.count_nonzero);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--count-nonzero".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--count-nonzero")
+        );
+    }
+
+    #[test]
+    fn test_config_with_head_bytes() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--head-bytes".to_string(),
+            "4K".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.head_bytes, Some(4096));
+
+        for value in ["0", "lots"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--head-bytes".to_string(),
+                value.to_string(),
+                "rust".to_string(),
+            ];
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--head-bytes")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_separators() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--match-separator".to_string(),
+            "=>".to_string(),
+            "--context-separator".to_string(),
+            "|".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.match_separator.as_deref(), Some("=>"));
+        assert_eq!(config.context_separator.as_deref(), Some("|"));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "--context-separator".to_string(),
+            "".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--context-separator")
+        );
+    }
+
+    #[test]
+    fn test_config_with_output_separator() {
+        for separator in ["---", ""] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--output-separator".to_string(),
+                separator.to_string(),
+                "rust".to_string(),
+            ];
+            let config = Config::build(args.into_iter()).unwrap();
+            assert_eq!(config.output_separator.as_deref(), Some(separator));
+        }
+
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "--output-separator".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--output-separator")
+        );
+    }
+
+    #[test]
+    fn test_config_with_no_unicode() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--no-unicode".to_string(),
+            r"\w+".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.no_unicode);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--no-unicode".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--no-unicode")
+        );
+    }
+
+    #[test]
+    fn test_config_with_regex_flags() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--regex-flags".to_string(),
+            "imsx".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.regex_flags.as_deref(), Some("imsx"));
+
+        for args in [
+            vec!["-r", "--regex-flags", "iu", "rust"],
+            vec!["-r", "--regex-flags", "", "rust"],
+            vec!["--regex-flags", "i", "rust"],
+        ] {
+            let args = std::iter::once("minigrep")
+                .chain(args)
+                .map(String::from)
+                .collect::<Vec<_>>();
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--regex-flags")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_context_first_only() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--context-first-only".to_string(),
+            "-A".to_string(),
+            "2".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.context_first_only);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--context-first-only".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--context-first-only")
+        );
+    }
+
+    #[test]
+    fn test_config_with_trim_context_blank() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--trim-context-blank".to_string(),
+            "-C".to_string(),
+            "2".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.trim_context_blank);
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--trim-context-blank".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--trim-context-blank")
+        );
+    }
+
+    #[test]
+    fn test_config_with_indent_context() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--indent-context".to_string(),
+            "4".to_string(),
+            "-C".to_string(),
+            "1".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.indent_context, 4);
+
+        for args in [
+            vec!["minigrep", "--indent-context", "wide", "-C", "1", "rust"],
+            vec!["minigrep", "--indent-context", "4", "rust"],
+        ] {
+            let result = Config::build(args.into_iter().map(String::from));
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--indent-context")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_invert_match() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-v".to_string(),
+            "-C".to_string(),
+            "1".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.invert_match);
+        assert_eq!((config.before_context, config.after_context), (1, 1));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "-v".to_string(),
+            "--slurp".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref a, ref b)) if a == "--invert-match" && b == "--slurp"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_sort_matches() {
+        for (flag, expected) in [
+            ("--sort-matches=text", MatchOrder::Text),
+            ("--sort-matches=numeric", MatchOrder::Numeric),
+        ] {
+            let args = vec!["minigrep".to_string(), flag.to_string(), "rust".to_string()];
+            let config = Config::build(args.into_iter()).unwrap();
+            assert_eq!(config.sort_matches, Some(expected));
+        }
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--sort-matches=size".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--sort-matches=size")
+        );
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--sort-matches=text".to_string(),
+            "-C".to_string(),
+            "1".to_string(),
+            "rust".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref a, ref b)) if a == "--sort-matches" && b == "--context"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_fuzzy() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--fuzzy".to_string(),
+            "1".to_string(),
+            "color".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.fuzzy, Some(1));
+
+        let args = vec![
+            "minigrep".to_string(),
+            "--fuzzy".to_string(),
+            "one".to_string(),
+            "color".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--fuzzy")
+        );
+
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--fuzzy".to_string(),
+            "1".to_string(),
+            "colou?r".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(
+            result,
+            Err(ApplicationError::ConflictingFlags(ref a, ref b)) if a == "--fuzzy" && b == "--use-regex"
+        ));
+    }
+
+    #[test]
+    fn test_config_with_reverse() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--reverse".to_string(),
+            "rust".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.reverse);
+
+        for (flag, conflict) in [
+            ("-A", "--context"),
+            ("--sort-matches=text", "--sort-matches"),
+        ] {
+            let mut args = vec!["minigrep".to_string(), "--reverse".to_string()];
+            args.push(flag.to_string());
+            if flag == "-A" {
+                args.push("1".to_string());
+            }
+            args.push("rust".to_string());
+
+            let result = Config::build(args.into_iter());
+            assert!(matches!(
+                result,
+                Err(ApplicationError::ConflictingFlags(ref a, ref b)) if a == "--reverse" && b == conflict
+            ));
+        }
+    }
+
+    #[test]
+    fn test_config_with_invalid_sort() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--sort=name".to_string(),
+            "rust".to_string(),
+        ];
+
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--sort=name"),
+            "Expected InvalidFlag error with '--sort=name', but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_config_with_range() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--range".to_string(),
+            "1000:2000".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.range, Some((1000, 2000)));
+        assert_eq!(config.query, "rust");
+    }
+
+    #[test]
+    fn test_config_with_invalid_range() {
+        for value in ["20:10", "0:5", "10", "a:b", ":5"] {
+            let args = vec![
+                "minigrep".to_string(),
+                "--range".to_string(),
+                value.to_string(),
+                "rust".to_string(),
+            ];
+
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--range"),
+                "Expected InvalidFlag error for '{}', but got {:?}",
+                value,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_missing_range() {
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "--range".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(matches!(result, Err(ApplicationError::InvalidFlag(_))));
+    }
+
+    #[test]
+    fn test_config_with_section() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--section".to_string(),
+            r"^\[(.+)\]$".to_string(),
+            "--section-name".to_string(),
+            "server".to_string(),
+            "port".to_string(),
+            "app.ini".to_string(),
+        ];
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.section.as_deref(), Some(r"^\[(.+)\]$"));
+        assert_eq!(config.section_name.as_deref(), Some("server"));
+
+        for (flag, value) in [("--section", r"^\["), ("--section-name", "server")] {
+            let args = vec![
+                "minigrep".to_string(),
+                flag.to_string(),
+                value.to_string(),
+                "port".to_string(),
+            ];
+            let result = Config::build(args.into_iter());
+            assert!(
+                matches!(result, Err(ApplicationError::InvalidFlag(ref invalid)) if invalid == flag)
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_skip_files() {
+        let args = vec![
+            "minigrep".to_string(),
+            "--skip-files".to_string(),
+            r"\.log$".to_string(),
+            "rust".to_string(),
+            "src".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(config.skip_files, Some(r"\.log$".to_string()));
+        assert_eq!(config.query, "rust");
+    }
+
+    #[test]
+    fn test_config_with_stdin_dash() {
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "poem.txt".to_string(),
+            "-".to_string(),
+        ];
+
+        let config = Config::build(args.into_iter()).unwrap();
+        assert_eq!(
+            config.file_paths,
+            vec!["poem.txt".to_string(), "-".to_string()]
+        );
+        assert!(!config.read_from_stdin);
+    }
+
+    #[test]
+    fn test_invalid_flag() {
+        let args = vec![
+            "minigrep".to_string(),
+            "rust".to_string(),
+            "--unknown".to_string(),
+            "poem.txt".to_string(),
+        ];
+
+        let result = Config::build(args.into_iter());
+
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidFlag(ref flag)) if flag == "--unknown"),
+            "Expected InvalidFlag error with '--unknown', but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_not_enough_arguments() {
+        let args = vec!["minigrep".to_string()];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::NotEnoughArguments)),
+            "Expected NotEnoughArguments error, but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_missing_query_or_file_paths() {
+        let args = vec!["minigrep".to_string(), "-i".to_string()];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::NotEnoughArguments)),
+            "Expected NotEnoughArguments error, but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_help_requested() {
+        let args = vec!["minigrep".to_string(), "--help".to_string()];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::HelpRequested)),
+            "Expected HelpRequested error, but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_regex_help_requested() {
+        let args = vec![
+            "minigrep".to_string(),
+            "-r".to_string(),
+            "--help-regex".to_string(),
+        ];
+        let result = Config::build(args.into_iter());
+        assert!(
+            matches!(result, Err(ApplicationError::RegexHelpRequested)),
+            "Expected RegexHelpRequested error, but got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_conflicting_flags() {
+        for (flags, first, second) in [
+            (
+                vec!["-q", "--print-first-match"],
+                "--quiet",
+                "--print-first-match",
+            ),
+            (
+                vec!["--passthrough", "--first-only"],
+                "--passthrough",
+                "--first-only",
+            ),
+            (
+                vec!["--passthrough", "--extract", "1"],
+                "--passthrough",
+                "--extract",
+            ),
+            (
+                vec!["--passthrough", "--extract-name", "word"],
+                "--passthrough",
+                "--extract-name",
+            ),
+            (
+                vec!["--name-only", "--passthrough"],
+                "--passthrough",
+                "--name-only",
+            ),
+            (
+                vec!["--extract", "1", "--extract-name", "word"],
+                "--extract",
+                "--extract-name",
+            ),
+            (
+                vec!["--name-only", "--extract", "1"],
+                "--name-only",
+                "--extract",
+            ),
+            (
+                vec!["--name-only", "--extract-name", "word"],
+                "--name-only",
+                "--extract-name",
+            ),
+        ] {
+            let args = ["minigrep", "-r"]
+                .into_iter()
+                .chain(flags)
+                .chain(["(?P<word>rust)"])
+                .map(str::to_string);
+
+            let result = Config::build(args);
+            assert!(
+                matches!(result, Err(ApplicationError::ConflictingFlags(ref a, ref b)) if a == first && b == second),
+                "Expected ConflictingFlags({}, {}), but got {:?}",
+                first,
+                second,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_compatible_flags() {
+        for flags in [
+            vec!["-r", "--first-only", "--extract", "1", "--unique", "-q"],
+            vec![
+                "--passthrough",
+                "--dim-context",
+                "--unique",
+                "--print-first-match",
+            ],
+            vec!["--name-only", "-q", "-R"],
+        ] {
+            let args = ["minigrep"]
+                .into_iter()
+                .chain(flags)
+                .chain(["(rust)"])
+                .map(str::to_string);
+
+            let result = Config::build(args);
+            assert!(result.is_ok(), "Expected Ok, but got {:?}", result);
+        }
     }
 }
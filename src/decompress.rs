@@ -0,0 +1,103 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// The gzip magic bytes followed by the deflate compression method, the only one in use.
+const GZIP_HEADER: &[u8] = &[0x1f, 0x8b, 0x08];
+/// The bzip2 stream magic; a block size digit and a block or end-of-stream magic follow it.
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const BZIP2_BLOCK_MAGIC: &[u8] = &[0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+const BZIP2_END_MAGIC: &[u8] = &[0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+/// Decompresses `bytes` with `gzip -dc` or `bzip2 -dc` when they start with that format's full
+/// header, so `cat app.log.gz | greplite error` searches the log itself. Any other input, and
+/// input the tool fails to decompress, is returned unchanged.
+pub fn decompress(bytes: Vec<u8>) -> Vec<u8> {
+    let program = if bytes.starts_with(GZIP_HEADER) {
+        "gzip"
+    } else if is_bzip2(&bytes) {
+        "bzip2"
+    } else {
+        return bytes;
+    };
+
+    run_decompressor(program, &bytes).unwrap_or(bytes)
+}
+
+fn is_bzip2(bytes: &[u8]) -> bool {
+    match bytes.strip_prefix(BZIP2_MAGIC) {
+        Some([b'1'..=b'9', rest @ ..]) => {
+            rest.starts_with(BZIP2_BLOCK_MAGIC) || rest.starts_with(BZIP2_END_MAGIC)
+        }
+        _ => false,
+    }
+}
+
+fn run_decompressor(program: &str, bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut child = Command::new(program)
+        .arg("-dc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdin = child.stdin.take()?;
+
+    // Feed the input from another thread so a large output can't fill the pipe and stall both.
+    let (fed, output) = thread::scope(|scope| {
+        let feeder = scope.spawn(move || stdin.write_all(bytes));
+        let output = child.wait_with_output();
+        (feeder.join(), output)
+    });
+    let output = output.ok()?;
+
+    (matches!(fed, Ok(Ok(()))) && output.status.success()).then_some(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = b"rust 1\nc++\nrust 2\n";
+
+    #[test]
+    fn test_decompress_gzip() {
+        let compressed = vec![
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x2b, 0x2a, 0x2d, 0x2e,
+            0x51, 0x30, 0xe4, 0x4a, 0xd6, 0xd6, 0xe6, 0x2a, 0x02, 0x31, 0x8d, 0xb8, 0x00, 0xcd,
+            0x0d, 0xbf, 0x1c, 0x12, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(decompress(compressed), CONTENT);
+    }
+
+    #[test]
+    fn test_decompress_bzip2() {
+        let compressed = vec![
+            0x42, 0x5a, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0x52, 0xb0, 0xf6, 0xd4,
+            0x00, 0x00, 0x05, 0x59, 0x80, 0x00, 0x10, 0x40, 0x08, 0x30, 0x00, 0x08, 0x00, 0x1e,
+            0x00, 0x20, 0x00, 0x31, 0x00, 0x30, 0x12, 0x93, 0x0d, 0x27, 0xa5, 0x5d, 0xfc, 0xf2,
+            0x42, 0x86, 0x31, 0x9c, 0x2e, 0xe4, 0x8a, 0x70, 0xa1, 0x20, 0xa5, 0x61, 0xed, 0xa8,
+        ];
+
+        assert_eq!(decompress(compressed), CONTENT);
+    }
+
+    #[test]
+    fn test_decompress_passes_plain_input_through() {
+        assert_eq!(decompress(CONTENT.to_vec()), CONTENT);
+    }
+
+    #[test]
+    fn test_decompress_leaves_lookalike_text_alone() {
+        for text in [
+            b"BZh is a word\n".as_slice(),
+            b"BZh9 but no block\n",
+            b"\x1f\x8b\x08 corrupt gzip\n",
+        ] {
+            assert_eq!(decompress(text.to_vec()), text);
+        }
+    }
+}
@@ -4,12 +4,22 @@ use std::{fmt, io};
 pub enum ApplicationError {
     NotEnoughArguments,
     InvalidRegex(String),
+    RegexTooLarge(String),
+    NonUtf8Regex(String),
     FileNotFound(String),
+    InvalidEncoding(String),
+    IsADirectory(String),
+    PreprocessorFailed(String),
     DirectoryReadError(String),
     DirectoryWithoutRecursive,
     InvalidFlag(String),
+    ConflictingFlags(String, String),
+    UnknownFileType(String),
     IOError(io::Error),
     HelpRequested,
+    RegexHelpRequested,
+    Timeout,
+    Interrupted,
 }
 
 impl fmt::Display for ApplicationError {
@@ -24,9 +34,32 @@ impl fmt::Display for ApplicationError {
             ApplicationError::InvalidRegex(query) => {
                 write!(f, "Error: Invalid regular expression: '{}'", query)
             }
+            ApplicationError::RegexTooLarge(query) => {
+                write!(
+                    f,
+                    "Error: Regular expression '{}' exceeds the configured size limit.",
+                    query
+                )
+            }
+            ApplicationError::NonUtf8Regex(query) => {
+                write!(
+                    f,
+                    "Error: Regular expression '{}' can match part of a multi-byte character with --no-unicode. Use an ASCII class such as [[:ascii:]], or wrap that part in (?u:...).",
+                    query
+                )
+            }
             ApplicationError::FileNotFound(file) => {
                 write!(f, "Error: File '{}' not found.", file)
             }
+            ApplicationError::InvalidEncoding(file) => {
+                write!(f, "Error: File '{}' is not valid UTF-8.", file)
+            }
+            ApplicationError::IsADirectory(path) => {
+                write!(f, "Error: '{}' is a directory, not a file.", path)
+            }
+            ApplicationError::PreprocessorFailed(command) => {
+                write!(f, "Error: Preprocessor command '{}' failed.", command)
+            }
             ApplicationError::DirectoryReadError(path) => {
                 write!(f, "Error reading directory '{}'.", path)
             }
@@ -36,23 +69,64 @@ impl fmt::Display for ApplicationError {
             ApplicationError::InvalidFlag(flag) => {
                 write!(f, "Error: Invalid flag '{}'.", flag)
             }
+            ApplicationError::ConflictingFlags(first, second) => {
+                write!(
+                    f,
+                    "Error: Flags '{}' and '{}' cannot be used together.",
+                    first, second
+                )
+            }
+            ApplicationError::UnknownFileType(name) => {
+                write!(f, "Error: Unknown file type '{}'.", name)
+            }
             ApplicationError::IOError(e) => write!(f, "I/O Error: {}", e),
             ApplicationError::HelpRequested => write!(f, "Help requested."),
+            ApplicationError::RegexHelpRequested => write!(f, "Regex help requested."),
+            ApplicationError::Timeout => write!(f, "Error: Search timed out."),
+            ApplicationError::Interrupted => write!(f, "Search interrupted."),
         }
     }
 }
 
 impl ApplicationError {
+    /// Maps a failure to read `path` as text, telling invalid UTF-8 apart from a missing or
+    /// unreadable file.
+    pub fn from_read_error(path: &str, error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::InvalidData {
+            ApplicationError::InvalidEncoding(path.to_string())
+        } else {
+            ApplicationError::FileNotFound(path.to_string())
+        }
+    }
+
     pub fn handle_error(&self) {
         match self {
             ApplicationError::HelpRequested => print_help(),
+            ApplicationError::RegexHelpRequested => print_regex_help(),
             ApplicationError::NotEnoughArguments => eprintln!("{}", self),
             ApplicationError::InvalidRegex(_) => eprintln!("{}", self),
+            ApplicationError::RegexTooLarge(_) => eprintln!("{}", self),
+            ApplicationError::NonUtf8Regex(_) => eprintln!("{}", self),
             ApplicationError::FileNotFound(_) => eprintln!("{}", self),
+            ApplicationError::InvalidEncoding(_) => eprintln!("{}", self),
+            ApplicationError::IsADirectory(_) => eprintln!("{}", self),
+            ApplicationError::PreprocessorFailed(_) => eprintln!("{}", self),
             ApplicationError::InvalidFlag(_) => eprintln!("{}", self),
+            ApplicationError::ConflictingFlags(..) => eprintln!("{}", self),
+            ApplicationError::UnknownFileType(_) => eprintln!("{}", self),
             ApplicationError::IOError(_) => eprintln!("{}", self),
             ApplicationError::DirectoryReadError(_) => eprintln!("{}", self),
             ApplicationError::DirectoryWithoutRecursive => eprintln!("{}", self),
+            ApplicationError::Timeout => eprintln!("{}", self),
+            ApplicationError::Interrupted => eprintln!("{}", self),
+        }
+    }
+
+    /// The process exit status for this error: 130 after Ctrl-C, like a shell, and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ApplicationError::Interrupted => 130,
+            _ => 1,
         }
     }
 }
@@ -64,14 +138,114 @@ fn print_help() {
     println!("  greplite [OPTION]... PATTERN [FILE]...");
     println!();
     println!("Search for PATTERN in each FILE or standard input.");
+    println!("A FILE of '-' reads standard input in that position.");
     println!();
     println!("Options:");
     println!("  -i, --ignore-case       Perform case-insensitive matching");
     println!("  -n, --line-numbers      Show line numbers with output lines");
+    println!("      --align-numbers     Right-align line numbers within each file (with -n)");
+    println!("      --relative-numbers  Number context lines relative to the match (-n, context)");
+    println!("      --line-numbers-only Print only the line numbers of matching lines");
+    println!("  -b, --byte-offset       Show the byte offset of each output line");
+    println!("      --global-numbers    Prefix matching lines with a running #N across all files");
     println!("  -r, --use-regex         Treat PATTERN as a regular expression");
+    println!("      --regex-flags FLAGS Set regex flags i, m, s and x (requires -r)");
+    println!("      --no-unicode        Make regex classes like \\w ASCII-only (requires -r)");
+    println!("  -F, --fixed-strings     Treat PATTERN as a literal string, even with -r");
+    println!("  -v, --invert-match      Select lines that do not match PATTERN");
+    println!("  -w, --word-regexp       Only match PATTERN as a whole word");
+    println!("      --no-unicode-word   With -w, treat only [A-Za-z0-9_] as word characters");
     println!("  -R, --recursive         Search recursively in directories.");
+    println!("      --auto-recursive    Search directory arguments recursively without -R");
+    println!("  -S, --follow            Follow symlinked directories when recursing");
+    println!("      --hidden            Search hidden files and directories when recursing");
+    println!("  -u, --unrestricted      Repeat (-uu, -uuu) to search hidden, then non-UTF-8 files");
+    println!("      --binary-text-preview  Search non-UTF-8 files, showing control bytes as \\xNN");
+    println!("  -U, --multiline         Let regex matches span lines (requires -r)");
+    println!("      --slurp             Match against each whole file and print only the matches");
+    println!("  -f, --file FILE         Read patterns from FILE, one per line");
+    println!("      --comment-char C    Treat pattern file lines starting with C as comments");
+    println!("      --no-comments       Treat every non-blank pattern file line as a pattern");
+    println!("      --extract N         Print only capture group N of each match (requires -r)");
+    println!("      --extract-name NAME Print only the named capture group of each match");
+    println!("      --first-per-line    Print only the first extracted match of each line");
+    println!("      --min-len N         Ignore regex matches shorter than N characters");
+    println!("      --fuzzy N           Match PATTERN with up to N character edits (no regex)");
+    println!("      --replace TEXT      Print matching lines with each match replaced by TEXT");
+    println!("      --in-place          Write replacements back to the files (requires --replace)");
+    println!("      --replace-dry-run   Show each line before and after --replace");
+    println!(
+        "      --in-place-backup SUFFIX  Like --in-place, keeping a copy of each file with SUFFIX"
+    );
     println!("  -c, --color             Highlight matching text in output");
+    println!("      --highlight-style=STYLE  Highlight with color, bold, underline, reverse or");
+    println!("                          bold-underline");
+    println!("      --dim-context       Dim non-matching text (with --passthrough or context)");
+    println!("      --color=WHEN        Highlight 'always', 'never' or 'auto' (honors NO_COLOR)");
+    println!("  -q, --quiet             Print nothing and stop at the first match");
+    println!("      --json-array        Print all matches as one JSON array at the end");
+    println!("      --exit-on-error     Stop at the first unreadable file when recursing");
+    println!("  -s, --no-messages       Suppress messages about unreadable files");
+    println!("      --print-first-match Print the first matching file name and stop");
+    println!("  -L, --files-without-match  Print the names of files with no matches");
+    println!("      --first-only        Print only the first matching line of each file");
+    println!("      --max-results N     Stop after printing N matching lines in total");
+    println!("      --count             Print only the number of matching lines in each file");
+    println!("      --count-distinct    Print only the number of distinct matching lines per file");
+    println!("      --count-nonzero     With --count, leave out files with no matches");
+    println!("      --files-with-count[=desc]  Print FILE:COUNT for files with matches");
+    println!("      --multi-match-only  Print only lines where PATTERN matches two or more times");
+    println!("      --unique            Print each distinct matching line once per file");
+    println!("  -Z, --null              Terminate printed file names with a NUL byte");
+    println!("      --input TEXT        Search TEXT instead of files or standard input");
+    println!("      --label NAME        Use NAME as the file name for standard input");
+    println!("      --pre COMMAND       Search the output of COMMAND run on each file");
+    println!("      --heading           Print the file name once above its matches");
+    println!("      --path-absolute     Print file names as absolute paths");
+    println!("      --path-relative DIR Print file names relative to DIR");
+    println!("      --passthrough       Print every line of files that contain a match");
+    println!("      --trim              Strip leading whitespace from output lines");
+    println!("      --tabsize N         Expand tabs in output lines to tab stops every N columns");
+    println!("  -M, --max-columns N     Truncate printed lines to N characters");
+    println!("      --preview-match     With -M, show a cut-off match instead of the line's start");
+    println!("      --sort=KEY          Sort directory entries by path, modified or size");
+    println!("      --sort-matches=KEY  Sort each file's matching lines by text or numeric value");
+    println!("      --reverse           Print each file's matching lines from last to first");
+    println!("  -A, --after-context N   Print N lines of context after each match");
+    println!("  -B, --before-context N  Print N lines of context before each match");
+    println!("  -C, --context N         Print N lines of context before and after each match");
+    println!("      --context-first-only  Only print context around the first match in each file");
+    println!("      --trim-context-blank  Don't print blank context lines");
+    println!("      --indent-context N  Indent context lines by N spaces");
+    println!("      --match-separator SEP   Use SEP instead of ':' after matching line prefixes");
+    println!("      --context-separator SEP Use SEP instead of '-' after context line prefixes");
+    println!("      --output-separator SEP  Print SEP on its own line between files' results");
+    println!("      --head-bytes SIZE   Only search the first SIZE bytes of each file (e.g. 4K)");
+    println!("      --range START:END   Only search lines START through END");
+    println!("      --skip-files REGEX  Skip files whose names match REGEX when recursing");
+    println!("      --field N           Only match against field N of each line (1-based)");
+    println!("      --delimiter CHAR    Split fields for --field at CHAR (default ',')");
+    println!("      --section REGEX     Split files into sections at lines matching REGEX");
+    println!("      --section-name NAME Only search sections named NAME (requires --section)");
+    println!("      --type NAME         Only search files of type NAME when recursing (e.g. rust)");
+    println!("      --type-not NAME     Skip files of type NAME when recursing");
+    println!("      --newer-than AGE    Skip files modified more than AGE ago (e.g. 24h, 7d)");
+    println!("      --older-than AGE    Skip files modified less than AGE ago when recursing");
+    println!("      --name-pattern GLOB Only search files whose names match GLOB when recursing");
+    println!("      --name-only         Match PATTERN against file names instead of contents");
+    println!("      --regex-size-limit SIZE  Limit the compiled regex size (e.g. 10M)");
+    println!("      --dfa-size-limit SIZE    Limit the regex DFA cache size (e.g. 2M)");
+    println!("      --progress          Show a running file count on stderr when recursing");
+    println!("      --no-glob           Don't expand globs such as '*.log' in file arguments");
+    println!("      --count-files       Print the number of files searched to stderr");
+    println!("      --count-chars       Print the chars and bytes in matching lines to stderr");
+    println!("      --match-histogram   Print how many lines had 1, 2, ... matches to stderr");
+    println!("      --verbose           Note on stderr when nothing matched");
+    println!("      --top N             Print the N files with the most matching lines to stderr");
+    println!("      --timeout MS        Abort the search after MS milliseconds");
+    println!("      --config PATH       Read default flags from PATH (or $GREPLITE_CONFIG_PATH)");
     println!("  -h, --help              Display this help and exit");
+    println!("      --help-regex        Display the supported regex syntax and exit");
     println!();
     println!("Examples:");
     println!("  greplite -i \"rust\" file1.txt       # Case-insensitive search for 'rust'");
@@ -81,9 +255,65 @@ fn print_help() {
     );
     println!("  greplite -i -n \"hello\" file1.txt file2.txt # Case-insensitive search with line numbers");
     println!();
+    println!(
+        "When -F and -r are both given, -F takes precedence: PATTERN is matched literally while"
+    );
+    println!("other regex options such as -U still apply.");
+    println!();
+    println!("Some flags cannot be combined: --slurp with -U or --passthrough;");
+    println!("-q with --print-first-match; --sort-matches with context;");
+    println!("--reverse with context or --sort-matches; --fuzzy with -r, -w or -f;");
+    println!("-L with --print-first-match, --count or --count-distinct;");
+    println!("--files-with-count with -L or --json-array;");
+    println!("--field with -U or --slurp;");
+    println!("--json-array with -q, --count, --count-distinct, -L or --print-first-match;");
+    println!("--in-place with --head-bytes or --replace-dry-run;");
+    println!("-v with --slurp, -U, --extract, --extract-name or --multi-match-only;");
+    println!("--slurp with --multi-match-only; --passthrough with");
+    println!(
+        "--first-only, --extract, --extract-name or --name-only; --extract with --extract-name;"
+    );
+    println!("and --name-only with --extract or --extract-name.");
+    println!();
+    println!("Defaults from the --config file (key = value lines) and then the flags in the");
+    println!("GREPLITE_OPTS environment variable are applied before the command-line");
+    println!("arguments. GREPLITE_OPTS may only hold flags that take no separate value.");
+    println!();
+    println!("Exit status is 0 if a line matched, 1 if none did or an error occurred, and 130");
+    println!("if the search was interrupted.");
+    println!();
     println!("For more information, check the documentation or run the command with -h.");
 }
 
+fn print_regex_help() {
+    println!("Greplite regular expression syntax (used with -r)");
+    println!();
+    println!("Matching:");
+    println!("  .          Any character except newline (including newline with -U)");
+    println!("  [abc]      Any of a, b or c; [^abc] negates, [a-z] is a range");
+    println!("  \\d \\w \\s   Digit, word character, whitespace; \\D \\W \\S negate");
+    println!("  \\p{{Greek}}  Unicode class by name");
+    println!();
+    println!("Repetition:");
+    println!("  * + ?      Zero or more, one or more, zero or one");
+    println!("  {{n,m}}      Between n and m times; add ? for a lazy match");
+    println!();
+    println!("Anchors and groups:");
+    println!("  ^ $        Start and end of line");
+    println!("  \\b         Word boundary");
+    println!("  (a|b)      Capturing group with alternation; (?:...) does not capture");
+    println!("  (?P<name>...)  Named group, usable with --extract-name");
+    println!("  (?i)       Inline flags, e.g. case-insensitive");
+    println!();
+    println!("Not supported:");
+    println!("  Backreferences such as \\1, and lookaround such as (?=...) or (?<!...).");
+    println!(
+        "  Matching always runs in linear time, so no pattern can cause runaway backtracking."
+    );
+    println!();
+    println!("See https://docs.rs/regex for the full syntax.");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +335,26 @@ mod tests {
         assert_eq!(result, "Error: Invalid regular expression: '^[a-z'");
     }
 
+    #[test]
+    fn test_display_non_utf8_regex() {
+        let err = ApplicationError::NonUtf8Regex("a.b".to_string());
+        let result = format!("{}", err);
+        assert_eq!(
+            result,
+            "Error: Regular expression 'a.b' can match part of a multi-byte character with --no-unicode. Use an ASCII class such as [[:ascii:]], or wrap that part in (?u:...)."
+        );
+    }
+
+    #[test]
+    fn test_display_regex_too_large() {
+        let err = ApplicationError::RegexTooLarge("\\w{50}".to_string());
+        let result = format!("{}", err);
+        assert_eq!(
+            result,
+            "Error: Regular expression '\\w{50}' exceeds the configured size limit."
+        );
+    }
+
     #[test]
     fn test_display_file_not_found() {
         let err = ApplicationError::FileNotFound("file.txt".to_string());
@@ -112,6 +362,45 @@ mod tests {
         assert_eq!(result, "Error: File 'file.txt' not found.");
     }
 
+    #[test]
+    fn test_from_read_error() {
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        let invalid = io::Error::from(io::ErrorKind::InvalidData);
+
+        assert!(matches!(
+            ApplicationError::from_read_error("a.txt", missing),
+            ApplicationError::FileNotFound(ref path) if path == "a.txt"
+        ));
+        assert!(matches!(
+            ApplicationError::from_read_error("a.bin", invalid),
+            ApplicationError::InvalidEncoding(ref path) if path == "a.bin"
+        ));
+    }
+
+    #[test]
+    fn test_display_invalid_encoding() {
+        let err = ApplicationError::InvalidEncoding("image.png".to_string());
+        let result = format!("{}", err);
+        assert_eq!(result, "Error: File 'image.png' is not valid UTF-8.");
+    }
+
+    #[test]
+    fn test_display_is_a_directory() {
+        let err = ApplicationError::IsADirectory("src".to_string());
+        let result = format!("{}", err);
+        assert_eq!(result, "Error: 'src' is a directory, not a file.");
+    }
+
+    #[test]
+    fn test_display_preprocessor_failed() {
+        let err = ApplicationError::PreprocessorFailed("zstd -dc log.zst".to_string());
+        let result = format!("{}", err);
+        assert_eq!(
+            result,
+            "Error: Preprocessor command 'zstd -dc log.zst' failed."
+        );
+    }
+
     #[test]
     fn test_display_directory_read_error() {
         let err = ApplicationError::DirectoryReadError("/some/path".to_string());
@@ -141,10 +430,47 @@ mod tests {
         assert_eq!(result, "I/O Error: File not found");
     }
 
+    #[test]
+    fn test_display_regex_help_requested() {
+        let err = ApplicationError::RegexHelpRequested;
+        let result = format!("{}", err);
+        assert_eq!(result, "Regex help requested.");
+    }
+
+    #[test]
+    fn test_display_conflicting_flags() {
+        let err = ApplicationError::ConflictingFlags("--quiet".to_string(), "--unique".to_string());
+        let result = format!("{}", err);
+        assert_eq!(
+            result,
+            "Error: Flags '--quiet' and '--unique' cannot be used together."
+        );
+    }
+
+    #[test]
+    fn test_display_unknown_file_type() {
+        let err = ApplicationError::UnknownFileType("cobol".to_string());
+        let result = format!("{}", err);
+        assert_eq!(result, "Error: Unknown file type 'cobol'.");
+    }
+
     #[test]
     fn test_display_help_requested() {
         let err = ApplicationError::HelpRequested;
         let result = format!("{}", err);
         assert_eq!(result, "Help requested.");
     }
+
+    #[test]
+    fn test_display_timeout() {
+        let err = ApplicationError::Timeout;
+        let result = format!("{}", err);
+        assert_eq!(result, "Error: Search timed out.");
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(ApplicationError::Interrupted.exit_code(), 130);
+        assert_eq!(ApplicationError::Timeout.exit_code(), 1);
+    }
 }
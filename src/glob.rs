@@ -0,0 +1,206 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Matches `name` against a shell-style glob: `*` matches any run of characters, `?` matches
+/// one character and `[...]` matches one character from a set such as `[abc]`, `[a-z]` or
+/// `[!0-9]`. A `[` without a closing `]` is matched literally.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        let advance = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match match_class(&pattern[p..], name[n]) {
+                Some((matched, len)) => matched.then_some(len),
+                None => (name[n] == '[').then_some(1),
+            },
+            Some(&c) => (c == name[n]).then_some(1),
+            None => None,
+        };
+
+        match (advance, backtrack) {
+            (Some(len), _) => {
+                p += len;
+                n += 1;
+            }
+            (None, Some((star, from))) => {
+                p = star + 1;
+                n = from + 1;
+                backtrack = Some((star, from + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+pub fn matches_name_pattern(path: &Path, name_pattern: &Option<String>) -> bool {
+    let Some(name_pattern) = name_pattern else {
+        return true;
+    };
+
+    path.file_name()
+        .is_some_and(|name| glob_match(name_pattern, &name.to_string_lossy()))
+}
+
+pub fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Expands `pattern` against the filesystem, matching each path component that contains glob
+/// metacharacters against the entries of its directory. Hidden entries are only matched by a
+/// component that starts with `.`. The matches are returned sorted.
+pub fn expand_glob(pattern: &str) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::new()];
+
+    for component in Path::new(pattern).components() {
+        let part = component.as_os_str().to_string_lossy();
+
+        if !matches!(component, Component::Normal(_)) || !is_glob(&part) {
+            for path in &mut paths {
+                path.push(component);
+            }
+            continue;
+        }
+
+        paths = paths
+            .iter()
+            .flat_map(|dir| matching_entries(dir, &part))
+            .collect();
+    }
+
+    paths.retain(|path| path.exists());
+    paths.sort();
+    paths
+}
+
+fn matching_entries(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let read_from = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let Ok(entries) = fs::read_dir(read_from) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') || pattern.starts_with('.'))
+        .filter(|name| glob_match(pattern, name))
+        .map(|name| dir.join(name))
+        .collect()
+}
+
+/// Matches `c` against the `[...]` set at the start of `pattern`, returning whether it matched
+/// and the length of the set, or `None` if the set is never closed.
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = matches!(pattern.get(1), Some('!' | '^'));
+    let mut i = if negated { 2 } else { 1 };
+    let start = i;
+    let mut matched = false;
+
+    loop {
+        let first = *pattern.get(i)?;
+
+        if first == ']' && i > start {
+            break;
+        }
+
+        match (pattern.get(i + 1), pattern.get(i + 2)) {
+            (Some('-'), Some(&last)) if last != ']' => {
+                matched |= first <= c && c <= last;
+                i += 3;
+            }
+            _ => {
+                matched |= first == c;
+                i += 1;
+            }
+        }
+    }
+
+    Some((matched != negated, i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_glob_match_wildcards() {
+        assert!(glob_match("*.conf", "nginx.conf"));
+        assert!(glob_match("*.conf", ".conf"));
+        assert!(!glob_match("*.conf", "nginx.conf.bak"));
+        assert!(glob_match("app-?.log", "app-1.log"));
+        assert!(!glob_match("app-?.log", "app-10.log"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxbxxa"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("exact.txt", "exact.txt"));
+        assert!(!glob_match("exact.txt", "exact.txt2"));
+    }
+
+    #[test]
+    fn test_glob_match_classes() {
+        assert!(glob_match("log[0-9].txt", "log7.txt"));
+        assert!(!glob_match("log[0-9].txt", "logx.txt"));
+        assert!(glob_match("log[!0-9].txt", "logx.txt"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        assert!(glob_match("file[", "file["));
+        assert!(glob_match("caf[éè]", "café"));
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = temp_dir("glob");
+        fs::create_dir(dir.join("logs")).unwrap();
+
+        for name in ["a.txt", "b.txt", ".hidden.txt", "c.log", "logs/d.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let root = dir.to_str().unwrap();
+        assert!(is_glob("*.txt") && !is_glob("a.txt"));
+        assert_eq!(
+            expand_glob(&format!("{}/*.txt", root)),
+            vec![dir.join("a.txt"), dir.join("b.txt")]
+        );
+        assert_eq!(
+            expand_glob(&format!("{}/.*.txt", root)),
+            vec![dir.join(".hidden.txt")]
+        );
+        assert_eq!(
+            expand_glob(&format!("{}/l*/*.txt", root)),
+            vec![dir.join("logs/d.txt")]
+        );
+        assert!(expand_glob(&format!("{}/*.md", root)).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_matches_name_pattern() {
+        let pattern = Some("*.conf".to_string());
+
+        assert!(matches_name_pattern(Path::new("etc/app.conf"), &pattern));
+        assert!(!matches_name_pattern(
+            Path::new("etc.conf/app.txt"),
+            &pattern
+        ));
+        assert!(matches_name_pattern(Path::new("anything"), &None));
+    }
+}
@@ -1,58 +1,346 @@
+use crate::search::{substring_spans, CompiledQuery};
 use regex::Regex;
+use std::{borrow::Cow, ops::Range};
 
 const HIGHLIGHT_START: &str = "\x1b[1;33m";
 const HIGHLIGHT_END: &str = "\x1b[0m";
+const HEADING_START: &str = "\x1b[1;35m";
+const DIM_START: &str = "\x1b[2m";
+const REMOVED_START: &str = "\x1b[1;31m";
+const ADDED_START: &str = "\x1b[1;32m";
+const PATTERN_COLORS: [&str; 6] = [
+    HIGHLIGHT_START,
+    "\x1b[1;36m",
+    "\x1b[1;35m",
+    "\x1b[1;32m",
+    "\x1b[1;34m",
+    "\x1b[1;31m",
+];
 
-fn apply_highlight(text: &str) -> String {
-    format!("{}{}{}", HIGHLIGHT_START, text, HIGHLIGHT_END)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum HighlightStyle {
+    #[default]
+    Color,
+    Bold,
+    Underline,
+    Reverse,
+    BoldUnderline,
 }
 
-fn highlight_with_regex<'a>(regex: &Regex, line: &'a str) -> String {
-    let mut highlighted_line = String::from(line);
+impl HighlightStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "color" => Some(HighlightStyle::Color),
+            "bold" => Some(HighlightStyle::Bold),
+            "underline" => Some(HighlightStyle::Underline),
+            "reverse" => Some(HighlightStyle::Reverse),
+            "bold-underline" => Some(HighlightStyle::BoldUnderline),
+            _ => None,
+        }
+    }
+
+    fn sgr(self) -> &'static str {
+        match self {
+            HighlightStyle::Color => HIGHLIGHT_START,
+            HighlightStyle::Bold => "\x1b[1m",
+            HighlightStyle::Underline => "\x1b[4m",
+            HighlightStyle::Reverse => "\x1b[7m",
+            HighlightStyle::BoldUnderline => "\x1b[1;4m",
+        }
+    }
+
+    /// The sequence for matches of the pattern at `index`: with the default color style each
+    /// pattern gets its own color from the palette, other styles are the same for all patterns.
+    fn pattern_sgr(self, index: usize) -> &'static str {
+        match self {
+            HighlightStyle::Color => PATTERN_COLORS[index % PATTERN_COLORS.len()],
+            _ => self.sgr(),
+        }
+    }
+}
+
+fn apply_highlight(text: &str, start: &str) -> String {
+    format!("{}{}{}", start, text, HIGHLIGHT_END)
+}
+
+fn apply_dim(text: &str) -> String {
+    format!("{}{}{}", DIM_START, text, HIGHLIGHT_END)
+}
+
+pub fn highlight_heading(text: &str) -> String {
+    format!("{}{}{}", HEADING_START, text, HIGHLIGHT_END)
+}
+
+fn merge_spans(mut spans: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    spans.retain(|span| span.start < span.end);
+    spans.sort_by_key(|span| (span.start, span.end));
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(spans.len());
+
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+
+    merged
+}
+
+fn apply_spans(line: &str, spans: Vec<Range<usize>>, start: &str) -> String {
+    let mut highlighted_line = String::with_capacity(line.len());
+    let mut last_end = 0;
+
+    for span in merge_spans(spans) {
+        highlighted_line.push_str(&line[last_end..span.start]);
+        highlighted_line.push_str(&apply_highlight(&line[span.clone()], start));
+        last_end = span.end;
+    }
 
-    for mat in regex.find_iter(line) {
-        let matched_string = &line[mat.start()..mat.end()];
-        let highlighted = apply_highlight(matched_string);
-        highlighted_line = highlighted_line.replace(matched_string, &highlighted);
+    highlighted_line.push_str(&line[last_end..]);
+    highlighted_line
+}
+
+/// Highlights the matches of each regex in its own pattern color. Where matches of different
+/// patterns overlap, the earlier pattern wins.
+fn apply_pattern_spans(line: &str, regexes: &[Regex], style: HighlightStyle) -> String {
+    let mut owners: Vec<Option<usize>> = vec![None; line.len()];
+
+    for (index, regex) in regexes.iter().enumerate() {
+        for mat in regex.find_iter(line) {
+            for owner in owners[mat.range()]
+                .iter_mut()
+                .filter(|owner| owner.is_none())
+            {
+                *owner = Some(index);
+            }
+        }
+    }
+
+    let mut highlighted_line = String::with_capacity(line.len());
+    let mut start = 0;
+
+    while start < line.len() {
+        let owner = owners[start];
+        let end = owners[start..]
+            .iter()
+            .position(|other| *other != owner)
+            .map_or(line.len(), |len| start + len);
+
+        match owner {
+            Some(index) => highlighted_line.push_str(&format!(
+                "{}{}{}",
+                style.pattern_sgr(index),
+                &line[start..end],
+                HIGHLIGHT_END
+            )),
+            None => highlighted_line.push_str(&line[start..end]),
+        }
+        start = end;
     }
 
     highlighted_line
 }
 
-fn highlight_with_substring<'a>(query: &str, line: &'a str, ignore_case: bool) -> String {
-    let search_line = if ignore_case {
-        line.to_lowercase()
+/// Writes control characters other than tab, including the C1 range, as the `\xNN` escapes of
+/// their UTF-8 bytes, so searching a binary file with `--binary-text-preview` can't send escape
+/// sequences or bells to the terminal.
+pub fn escape_control_chars(line: &str) -> Cow<'_, str> {
+    if !line.chars().any(is_escaped) {
+        return Cow::Borrowed(line);
+    }
+
+    let mut escaped = String::with_capacity(line.len() + 8);
+
+    for c in line.chars() {
+        if is_escaped(c) {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                escaped.push_str(&format!("\\x{:02x}", byte));
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+fn is_escaped(c: char) -> bool {
+    c.is_control() && c != '\t'
+}
+
+/// Decodes `bytes` with each byte that isn't part of valid UTF-8 written as `\xNN`, so
+/// `--binary-text-preview` shows the raw byte instead of a replacement character.
+pub fn escape_invalid_utf8(bytes: &[u8]) -> String {
+    let mut decoded = String::with_capacity(bytes.len());
+
+    for chunk in bytes.utf8_chunks() {
+        decoded.push_str(chunk.valid());
+
+        for byte in chunk.invalid() {
+            decoded.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+
+    decoded
+}
+
+/// Truncates `line` to `max_columns` visible characters, not counting escape sequences, and
+/// notes how many characters were left out. A highlight cut off by the limit is closed.
+pub fn truncate_columns(line: &str, max_columns: usize) -> Cow<'_, str> {
+    let (window, columns) = column_window(line, 0, max_columns);
+
+    if columns <= max_columns {
+        return Cow::Borrowed(line);
+    }
+
+    Cow::Owned(format!("{} [... +{} chars]", window, columns - max_columns))
+}
+
+/// Like `truncate_columns`, but shows `max_columns` characters starting at the match at
+/// `column` (counted from zero), prefixed with the match's one-based column.
+pub fn preview_columns(line: &str, column: usize, max_columns: usize) -> String {
+    let (window, columns) = column_window(line, column, max_columns);
+    let hidden = columns.saturating_sub(column + max_columns);
+    let note = if hidden > 0 {
+        format!(" [... +{} chars]", hidden)
     } else {
-        line.to_string()
+        String::new()
     };
 
-    if let Some(pos) = search_line.find(query) {
-        let matched_str = &line[pos..pos + query.len()];
-        let highlighted = apply_highlight(matched_str);
-        line.replace(matched_str, &highlighted)
-    } else {
-        String::from(line)
+    format!("[match at col {}] {}{}", column + 1, window, note)
+}
+
+/// Returns the visible characters of `line` from column `skip` up to `skip + width`, keeping
+/// the escape sequences in effect for them, along with the line's total visible width.
+fn column_window(line: &str, skip: usize, width: usize) -> (String, usize) {
+    let mut window = String::with_capacity(line.len().min(width * 4));
+    let mut active: Option<&str> = None;
+    let mut started = false;
+    let mut columns = 0;
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let in_window = columns >= skip && columns < skip + width;
+
+        if in_window && !started {
+            started = true;
+            window.push_str(active.unwrap_or_default());
+        }
+
+        if c == '\x1b' && chars.next_if(|&(_, next)| next == '[').is_some() {
+            let end = chars
+                .find(|&(_, c)| ('\x40'..='\x7e').contains(&c))
+                .map_or(line.len(), |(end, c)| end + c.len_utf8());
+            let sequence = &line[index..end];
+
+            if columns < skip + width {
+                active = (sequence != HIGHLIGHT_END).then_some(sequence);
+            }
+            if in_window {
+                window.push_str(sequence);
+            }
+            continue;
+        }
+
+        if in_window {
+            window.push(c);
+        }
+        columns += 1;
+    }
+
+    if active.is_some() {
+        window.push_str(HIGHLIGHT_END);
+    }
+
+    (window, columns)
+}
+
+fn dim_outside_spans(line: &str, spans: Vec<Range<usize>>) -> String {
+    let mut dimmed_line = String::with_capacity(line.len());
+    let mut last_end = 0;
+
+    for span in merge_spans(spans) {
+        if last_end < span.start {
+            dimmed_line.push_str(&apply_dim(&line[last_end..span.start]));
+        }
+        dimmed_line.push_str(&line[span.clone()]);
+        last_end = span.end;
+    }
+
+    if last_end < line.len() {
+        dimmed_line.push_str(&apply_dim(&line[last_end..]));
     }
+
+    dimmed_line
 }
 
-pub fn highlight_match<'a>(
+fn regex_spans(regexes: &[Regex], line: &str) -> Vec<Range<usize>> {
+    regexes
+        .iter()
+        .flat_map(|regex| regex.find_iter(line).map(|mat| mat.range()))
+        .collect()
+}
+
+fn highlight_with_regex(regex: &Regex, line: &str, style: HighlightStyle) -> String {
+    apply_spans(
+        line,
+        regex_spans(std::slice::from_ref(regex), line),
+        style.sgr(),
+    )
+}
+
+fn highlight_with_substring(
     query: &str,
-    line: &'a str,
+    line: &str,
+    ignore_case: bool,
+    style: HighlightStyle,
+) -> String {
+    apply_spans(line, substring_spans(query, line, ignore_case), style.sgr())
+}
+
+pub fn highlight_patterns(regexes: &[Regex], line: &str, style: HighlightStyle) -> String {
+    apply_pattern_spans(line, regexes, style)
+}
+
+/// Highlights precomputed match `spans` of `line`, e.g. those limited to one field by `--field`.
+pub fn highlight_spans(line: &str, spans: Vec<Range<usize>>, style: HighlightStyle) -> String {
+    apply_spans(line, spans, style.sgr())
+}
+
+pub fn highlight_match(
+    query: &str,
+    line: &str,
     ignore_case: bool,
     regex: &Option<Regex>,
+    style: HighlightStyle,
 ) -> String {
     if let Some(regex) = regex {
-        highlight_with_regex(regex, line)
+        highlight_with_regex(regex, line, style)
     } else {
         let query = if ignore_case {
             query.to_lowercase()
         } else {
             query.to_string()
         };
-        highlight_with_substring(&query, line, ignore_case)
+        highlight_with_substring(&query, line, ignore_case, style)
     }
 }
 
+/// Colors the `spans` of `line` that a replacement removes, for `--replace-dry-run`.
+pub fn highlight_removed(line: &str, spans: Vec<Range<usize>>) -> String {
+    apply_spans(line, spans, REMOVED_START)
+}
+
+/// Colors the `spans` of a replaced line that a replacement added, for `--replace-dry-run`.
+pub fn highlight_added(line: &str, spans: Vec<Range<usize>>) -> String {
+    apply_spans(line, spans, ADDED_START)
+}
+
+pub fn dim_non_matches(query: &CompiledQuery, line: &str) -> String {
+    dim_outside_spans(line, query.spans(line))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,7 +349,7 @@ mod tests {
     fn test_apply_highlight() {
         let input = "Rust is powerful";
         let expected = "\x1b[1;33mRust is powerful\x1b[0m";
-        let result = apply_highlight(input);
+        let result = apply_highlight(input, HighlightStyle::Color.sgr());
         assert_eq!(result, expected);
     }
 
@@ -71,7 +359,7 @@ mod tests {
         let input = "Rust is powerful, and Rocks are heavy.";
         let expected = "\x1b[1;33mRust\x1b[0m is powerful, and \x1b[1;33mRocks\x1b[0m are heavy.";
 
-        let result = highlight_with_regex(&regex, input);
+        let result = highlight_with_regex(&regex, input, HighlightStyle::Color);
         assert_eq!(result, expected);
     }
 
@@ -81,7 +369,7 @@ mod tests {
         let query = "Rust";
         let expected = "\x1b[1;33mRust\x1b[0m is powerful, Rocks are heavy.";
 
-        let result = highlight_with_substring(query, input, false);
+        let result = highlight_with_substring(query, input, false, HighlightStyle::Color);
         assert_eq!(result, expected);
     }
 
@@ -91,7 +379,7 @@ mod tests {
         let query = "rust";
         let expected = "\u{1b}[1;33mRust\u{1b}[0m is powerful, Rocks are heavy.";
 
-        let result = highlight_with_substring(query, input, true);
+        let result = highlight_with_substring(query, input, true, HighlightStyle::Color);
         assert_eq!(result, expected);
     }
 
@@ -102,7 +390,220 @@ mod tests {
         let input = "Rust is powerful, and Rocks are heavy.";
         let expected = "\x1b[1;33mRust\x1b[0m is powerful, and \x1b[1;33mRocks\x1b[0m are heavy.";
 
-        let result = highlight_match(query, input, false, &Some(regex));
+        let result = highlight_match(query, input, false, &Some(regex), HighlightStyle::Color);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_highlight_heading() {
+        let result = highlight_heading("src/main.rs");
+        assert_eq!(result, "\x1b[1;35msrc/main.rs\x1b[0m");
+    }
+
+    #[test]
+    fn test_merge_spans_overlapping_and_adjacent() {
+        let spans = vec![4..6, 0..3, 2..4, 8..9];
+        assert_eq!(merge_spans(spans), vec![0..6, 8..9]);
+    }
+
+    #[test]
+    fn test_highlight_patterns_overlapping_matches() {
+        let regexes = vec![Regex::new("abc").unwrap(), Regex::new("bcd").unwrap()];
+        let expected = "\x1b[1;33mabc\x1b[0m\x1b[1;36md\x1b[0m";
+
+        let result = highlight_patterns(&regexes, "abcd", HighlightStyle::Color);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_highlight_patterns_separate_matches() {
+        let regexes = vec![Regex::new("ab").unwrap(), Regex::new("ef").unwrap()];
+        let expected = "\x1b[1;33mab\x1b[0mcd\x1b[1;36mef\x1b[0m";
+
+        let result = highlight_patterns(&regexes, "abcdef", HighlightStyle::Color);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_highlight_patterns_distinct_colors() {
+        let regexes = vec![Regex::new("error").unwrap(), Regex::new("warn").unwrap()];
+        let result = highlight_patterns(&regexes, "warn: error", HighlightStyle::Color);
+
+        assert_eq!(result, "\x1b[1;36mwarn\x1b[0m: \x1b[1;33merror\x1b[0m");
+    }
+
+    #[test]
+    fn test_highlight_patterns_repeated_and_adjacent_matches() {
+        let regexes = vec![Regex::new("a").unwrap(), Regex::new("b").unwrap()];
+        let result = highlight_patterns(&regexes, "aab", HighlightStyle::Color);
+
+        assert_eq!(result, "\x1b[1;33maa\x1b[0m\x1b[1;36mb\x1b[0m");
+    }
+
+    #[test]
+    fn test_highlight_patterns_with_style() {
+        let regexes = vec![Regex::new("ab").unwrap(), Regex::new("cd").unwrap()];
+        let result = highlight_patterns(&regexes, "abcd", HighlightStyle::Underline);
+
+        assert_eq!(result, "\x1b[4mab\x1b[0m\x1b[4mcd\x1b[0m");
+    }
+
+    #[test]
+    fn test_highlight_with_regex_repeated_match() {
+        let regex = Regex::new("a|aa").unwrap();
+        let expected = "\x1b[1;33ma\x1b[0mb\x1b[1;33ma\x1b[0m";
+
+        let result = highlight_with_regex(&regex, "aba", HighlightStyle::Color);
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_escape_control_chars() {
+        assert!(matches!(
+            escape_control_chars("plain\ttext é"),
+            Cow::Borrowed("plain\ttext é")
+        ));
+        assert_eq!(
+            escape_control_chars("rust\x1b[2J\x07\0end\x7f"),
+            r"rust\x1b[2J\x07\x00end\x7f"
+        );
+        assert_eq!(escape_control_chars("csi\u{9b}2J"), r"csi\xc2\x9b2J");
+    }
+
+    #[test]
+    fn test_escape_invalid_utf8() {
+        assert_eq!(escape_invalid_utf8(b"plain \xc3\xa9"), "plain é");
+        assert_eq!(
+            escape_invalid_utf8(b"rust\xff\xfe end\xc3"),
+            r"rust\xff\xfe end\xc3"
+        );
+    }
+
+    #[test]
+    fn test_truncate_columns() {
+        let line = "x".repeat(100);
+
+        assert_eq!(
+            truncate_columns(&line, 10),
+            format!("{} [... +90 chars]", "x".repeat(10))
+        );
+        assert_eq!(truncate_columns("short", 10), "short");
+        assert_eq!(truncate_columns("exactly10!", 10), "exactly10!");
+    }
+
+    #[test]
+    fn test_truncate_columns_respects_chars_and_escapes() {
+        assert_eq!(
+            truncate_columns("日本語のテキスト", 3),
+            "日本語 [... +5 chars]"
+        );
+
+        let line = "ab\x1b[1;33mcdef\x1b[0mgh";
+        assert_eq!(
+            truncate_columns(line, 4),
+            "ab\x1b[1;33mcd\x1b[0m [... +4 chars]"
+        );
+        assert_eq!(truncate_columns(line, 8), line);
+        assert_eq!(
+            truncate_columns(line, 6),
+            "ab\x1b[1;33mcdef\x1b[0m [... +2 chars]"
+        );
+    }
+
+    #[test]
+    fn test_preview_columns() {
+        let line = format!("{}needle{}", "x".repeat(50), "y".repeat(20));
+
+        assert_eq!(
+            preview_columns(&line, 50, 10),
+            "[match at col 51] needleyyyy [... +16 chars]"
+        );
+        assert_eq!(preview_columns(&line, 70, 10), "[match at col 71] yyyyyy");
+    }
+
+    #[test]
+    fn test_preview_columns_keeps_highlight() {
+        let line = "xxxxab\x1b[1;33mneedle\x1b[0mzz";
+
+        assert_eq!(
+            preview_columns(line, 6, 4),
+            "[match at col 7] \x1b[1;33mneed\x1b[0m [... +4 chars]"
+        );
+        assert_eq!(
+            preview_columns(line, 8, 3),
+            "[match at col 9] \x1b[1;33medl\x1b[0m [... +3 chars]"
+        );
+    }
+
+    #[test]
+    fn test_highlight_removed_and_added() {
+        assert_eq!(
+            highlight_removed("use foo;", std::iter::once(4..7).collect()),
+            "use \x1b[1;31mfoo\x1b[0m;"
+        );
+        assert_eq!(
+            highlight_added("use bar;", std::iter::once(4..7).collect()),
+            "use \x1b[1;32mbar\x1b[0m;"
+        );
+    }
+
+    #[test]
+    fn test_dim_non_matches() {
+        let expected = "\x1b[2mthe \x1b[0mrust\x1b[2m and \x1b[0mrust";
+
+        let result = dim_non_matches(
+            &CompiledQuery::new("rust", false, None),
+            "the rust and rust",
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_dim_non_matches_without_match() {
+        let result = dim_non_matches(&CompiledQuery::new("rust", false, None), "c++");
+        assert_eq!(result, "\x1b[2mc++\x1b[0m");
+    }
+
+    #[test]
+    fn test_dim_non_matches_with_regex() {
+        let regex = Regex::new(r"\d+").unwrap();
+        let expected = "42\x1b[2m apples\x1b[0m";
+
+        let result = dim_non_matches(&CompiledQuery::new("", false, Some(regex)), "42 apples");
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_highlight_style_sgr_sequences() {
+        for (name, sgr) in [
+            ("color", "\x1b[1;33m"),
+            ("bold", "\x1b[1m"),
+            ("underline", "\x1b[4m"),
+            ("reverse", "\x1b[7m"),
+            ("bold-underline", "\x1b[1;4m"),
+        ] {
+            let style = HighlightStyle::from_name(name).unwrap();
+
+            assert_eq!(style.sgr(), sgr);
+            assert_eq!(
+                apply_highlight("rust", style.sgr()),
+                format!("{}rust\x1b[0m", sgr)
+            );
+        }
+
+        assert_eq!(HighlightStyle::from_name("blink"), None);
+    }
+
+    #[test]
+    fn test_highlight_match_with_style() {
+        let result = highlight_match(
+            "rust",
+            "I love rust",
+            false,
+            &None,
+            HighlightStyle::Underline,
+        );
+
+        assert_eq!(result, "I love \x1b[4mrust\x1b[0m");
+    }
 }
@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a SIGINT handler that only sets a flag, so the search can stop at the next check
+/// and flush what it has already printed. Platforms without `sigaction` keep the default
+/// behaviour.
+pub fn install() {
+    if sys::install(on_interrupt) {
+        INSTALLED.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Storing to an atomic is async-signal-safe, unlike most other work a handler could do.
+extern "C" fn on_interrupt(_: std::ffi::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Returns the flag set by Ctrl-C once `install` has run, so library callers that never
+/// install the handler are not affected.
+pub fn flag() -> Option<&'static AtomicBool> {
+    INSTALLED.load(Ordering::SeqCst).then_some(&INTERRUPTED)
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::{ffi::c_int, mem, ptr};
+
+    pub fn install(handler: extern "C" fn(c_int)) -> bool {
+        // SAFETY: the action is fully initialised before use and the handler only stores to an
+        // atomic.
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            // Without `SA_RESTART` a read blocked on stdin fails with EINTR, so the flag is
+            // noticed. `SA_RESETHAND` restores the default, so a second Ctrl-C always kills.
+            action.sa_flags = libc::SA_RESETHAND;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGINT, &action, ptr::null_mut()) == 0
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub fn install(_: extern "C" fn(std::ffi::c_int)) -> bool {
+        false
+    }
+}
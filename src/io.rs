@@ -1,79 +1,4367 @@
-use crate::{highlight::highlight_match, search::search, ApplicationError, Config};
-use regex::Regex;
-use std::{fs, io::Read};
+use crate::{
+    config::{MatchOrder, PathStyle, SortBy},
+    glob::matches_name_pattern,
+    highlight::{
+        dim_non_matches, escape_control_chars, escape_invalid_utf8, highlight_added,
+        highlight_heading, highlight_match, highlight_patterns, highlight_removed, highlight_spans,
+        preview_columns, truncate_columns,
+    },
+    json::match_record,
+    regex::SearchPatterns,
+    replace::{replace_in_place, replace_line, replace_line_with_spans},
+    search::{
+        invert_matches, search_multiline, search_slurp, search_until, section_lines, with_context,
+        with_first_context, CompiledQuery, LineMatch,
+    },
+    stats::SearchStats,
+    types::matches_file_types,
+    ApplicationError, Config,
+};
+use regex::{Captures, Match, Regex};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
 
-pub fn process_input<R: Read>(
+const UTF8_BOM: char = '\u{FEFF}';
+/// Printed between groups of context lines that are not adjacent, within a file or across files.
+const GROUP_SEPARATOR: &str = "--";
+
+pub fn read_patterns(
+    file_path: &str,
+    comment_char: Option<char>,
+) -> Result<Vec<String>, ApplicationError> {
+    let content = fs::read_to_string(file_path)
+        .map_err(|error| ApplicationError::from_read_error(file_path, error))?;
+
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match comment_char {
+            Some(comment_char) if line.starts_with(comment_char) => None,
+            Some(comment_char) => match line.strip_prefix('\\') {
+                Some(rest) if rest.starts_with(comment_char) => Some(rest.to_string()),
+                _ => Some(line.to_string()),
+            },
+            None => Some(line.to_string()),
+        })
+        .collect())
+}
+
+pub fn process_input<R: Read, W: Write>(
     source: &str,
     reader: &mut R,
     config: &Config,
-    regex: &Option<Regex>,
-) -> Result<(), ApplicationError> {
-    let mut input = String::new();
+    patterns: &SearchPatterns,
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<ControlFlow<()>, ApplicationError> {
+    let bytes = read_input(reader, stats)?;
 
-    reader
-        .read_to_string(&mut input)
-        .map_err(|e| ApplicationError::IOError(e))?;
+    #[cfg(feature = "decompress")]
+    let bytes = crate::decompress::decompress(bytes);
 
-    let results = search(&config.query, &input, config.ignore_case, regex);
-    print_results(config, source, results, regex)
+    let input = String::from_utf8(bytes)
+        .map_err(|_| ApplicationError::InvalidEncoding(source.to_string()))?;
+
+    let results = search_content(config, &input, patterns, stats)?;
+    print_results(config, source, false, results, patterns, writer, stats)
+}
+
+/// Reads all of `reader` like `read_to_end`, but gives up with `Interrupted` when Ctrl-C cuts
+/// a read short, so waiting on a pipe or terminal that never closes can still be stopped.
+fn read_input<R: Read>(reader: &mut R, stats: &SearchStats) -> Result<Vec<u8>, ApplicationError> {
+    let mut bytes = Vec::new();
+    let mut buffer = [0; 64 * 1024];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(bytes),
+            Ok(read) => bytes.extend_from_slice(&buffer[..read]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(ApplicationError::IOError(error)),
+        }
+
+        stats.check_interrupted()?;
+    }
 }
 
-pub fn process_file(
+pub fn process_file<W: Write>(
     file_path: &str,
     config: &Config,
-    regex: &Option<Regex>,
-) -> Result<(), ApplicationError> {
-    let content = fs::read_to_string(file_path)
-        .map_err(|_| ApplicationError::FileNotFound(file_path.to_string()))?;
+    patterns: &SearchPatterns,
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<ControlFlow<()>, ApplicationError> {
+    if std::path::Path::new(file_path).is_dir() {
+        return Err(ApplicationError::IsADirectory(file_path.to_string()));
+    }
+
+    if let (true, Some(replacement)) = (config.in_place, &config.replace) {
+        stats.files_searched += 1;
+
+        if replace_in_place(file_path, replacement, config, &patterns.regex)? {
+            stats.files_with_matches += 1;
+        }
+
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    if let Some(command) = &config.preprocess_cmd {
+        let output = run_preprocessor(command, file_path)?;
+        let source = display_path(file_path, config);
+        let content = String::from_utf8(output)
+            .map_err(|_| ApplicationError::InvalidEncoding(source.to_string()))?;
+        stats.files_searched += 1;
+
+        let results = search_content(config, &content, patterns, stats)?;
+        return print_results(
+            config,
+            &source,
+            config.heading,
+            results,
+            patterns,
+            writer,
+            stats,
+        );
+    }
+
+    let content = match config.head_bytes {
+        Some(limit) => read_head(file_path, limit, config)?,
+        None => read_text(file_path, config)?,
+    };
+    stats.files_searched += 1;
+
+    let results = search_content(config, &content, patterns, stats)?;
+    print_results(
+        config,
+        &display_path(file_path, config),
+        config.heading,
+        results,
+        patterns,
+        writer,
+        stats,
+    )
+}
+
+/// Reads `file_path` as UTF-8, or with invalid bytes replaced when `binary_as_text` is set
+/// (`-uuu`) so files that are not text are searched instead of skipped.
+fn read_text(file_path: &str, config: &Config) -> Result<String, ApplicationError> {
+    if !config.binary_as_text {
+        return fs::read_to_string(file_path)
+            .map_err(|error| ApplicationError::from_read_error(file_path, error));
+    }
 
-    let results = search(&config.query, &content, config.ignore_case, regex);
-    print_results(config, file_path, results, regex)
+    let bytes =
+        fs::read(file_path).map_err(|error| ApplicationError::from_read_error(file_path, error))?;
+    Ok(String::from_utf8(bytes).unwrap_or_else(|error| decode_lossy(error.as_bytes(), config)))
 }
 
-pub fn process_directory(
+fn read_head(file_path: &str, limit: usize, config: &Config) -> Result<String, ApplicationError> {
+    let mut bytes = Vec::new();
+
+    fs::File::open(file_path)
+        .and_then(|file| file.take(limit as u64).read_to_end(&mut bytes))
+        .map_err(|error| ApplicationError::from_read_error(file_path, error))?;
+
+    let end = complete_utf8_len(&bytes);
+    Ok(decode_lossy(&bytes[..end], config))
+}
+
+/// Decodes `bytes` with invalid UTF-8 written as `\xNN` under `--binary-text-preview`, or
+/// replaced with U+FFFD otherwise.
+fn decode_lossy(bytes: &[u8], config: &Config) -> String {
+    if config.binary_text_preview {
+        escape_invalid_utf8(bytes)
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+/// Returns the length of `bytes` without a trailing multi-byte character that was cut short.
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(4) {
+        let byte = bytes[bytes.len() - back];
+
+        if byte & 0xC0 != 0x80 {
+            let width = byte.leading_ones() as usize;
+            return if width > back {
+                bytes.len() - back
+            } else {
+                bytes.len()
+            };
+        }
+    }
+
+    bytes.len()
+}
+
+fn display_path<'a>(file_path: &'a str, config: &Config) -> Cow<'a, str> {
+    let path = match &config.path_style {
+        Some(PathStyle::Absolute) => fs::canonicalize(file_path).ok(),
+        Some(PathStyle::Relative(base)) => fs::canonicalize(file_path)
+            .ok()
+            .zip(fs::canonicalize(base).ok())
+            .and_then(|(path, base)| path.strip_prefix(base).ok().map(PathBuf::from)),
+        None => None,
+    };
+
+    match path {
+        Some(path) => Cow::Owned(path.to_string_lossy().into_owned()),
+        None => Cow::Borrowed(file_path),
+    }
+}
+
+fn run_preprocessor(command: &str, file_path: &str) -> Result<Vec<u8>, ApplicationError> {
+    let failed = || ApplicationError::PreprocessorFailed(format!("{} {}", command, file_path));
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or_else(failed)?;
+
+    let output = Command::new(program)
+        .args(parts)
+        .arg(file_path)
+        .output()
+        .map_err(|_| failed())?;
+
+    if !output.status.success() {
+        return Err(failed());
+    }
+
+    Ok(output.stdout)
+}
+
+pub fn process_directory<W: Write>(
     dir_path: &std::path::Path,
     config: &Config,
-    regex: &Option<Regex>,
-) -> Result<(), ApplicationError> {
+    patterns: &SearchPatterns,
+    skip_files: &Option<Regex>,
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<ControlFlow<()>, ApplicationError> {
+    if config.follow_links {
+        if let Ok(canonical) = fs::canonicalize(dir_path) {
+            if !stats.visited_dirs.insert(canonical) {
+                return Ok(ControlFlow::Continue(()));
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+
     for entry in fs::read_dir(dir_path)
         .map_err(|_| ApplicationError::FileNotFound(dir_path.to_string_lossy().to_string()))?
     {
         let entry = entry
             .map_err(|_| ApplicationError::FileNotFound(dir_path.to_string_lossy().to_string()))?;
-        let path = entry.path();
+        paths.push(entry.path());
+    }
+
+    if let Some(sort_by) = config.sort_by {
+        sort_paths(&mut paths, sort_by);
+    }
 
-        if path.is_dir() {
-            process_directory(&path, config, regex)?;
+    for path in paths {
+        stats.check_interrupted()?;
+
+        if !config.hidden && is_hidden(&path) {
+            continue;
+        }
+
+        let result = if path.is_dir() {
+            if !config.follow_links && is_symlink(&path) {
+                continue;
+            }
+
+            process_directory(&path, config, patterns, skip_files, writer, stats)
+        } else if is_skipped(&path, skip_files)
+            || !matches_file_types(&path, &config.file_types, &config.excluded_types)
+            || !modified_within(&path, config)
+            || !matches_name_pattern(&path, &config.name_pattern)
+            || !is_first_visit(&path, stats)
+        {
+            Ok(ControlFlow::Continue(()))
+        } else if config.name_only {
+            print_name_match(&path, config, &patterns.regex, writer, stats)
         } else {
-            process_file(path.to_str().unwrap(), config, regex)?;
+            process_file(path.to_str().unwrap(), config, patterns, writer, stats)
+        };
+
+        let flow = match result {
+            Err(
+                error @ (ApplicationError::FileNotFound(_) | ApplicationError::InvalidEncoding(_)),
+            ) if !config.exit_on_error => {
+                if let Some(progress) = &mut stats.progress {
+                    progress.clear();
+                }
+                report_warning(config, &mut io::stderr(), &error);
+                ControlFlow::Continue(())
+            }
+            result => result?,
+        };
+        stats.report_progress(&path);
+
+        if flow.is_break() {
+            return Ok(flow);
         }
     }
 
-    Ok(())
+    Ok(ControlFlow::Continue(()))
 }
 
-fn print_results(
+/// Checks `--newer-than` and `--older-than` against the file's age. Files whose modification
+/// time cannot be read are let through so that searching them reports the problem.
+fn modified_within(path: &std::path::Path, config: &Config) -> bool {
+    if config.newer_than.is_none() && config.older_than.is_none() {
+        return true;
+    }
+
+    let Ok(modified) = fs::metadata(path).and_then(|meta| meta.modified()) else {
+        return true;
+    };
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO);
+
+    config.newer_than.is_none_or(|limit| age <= limit)
+        && config.older_than.is_none_or(|limit| age >= limit)
+}
+
+fn print_name_match<W: Write>(
+    path: &std::path::Path,
     config: &Config,
-    source: &str,
-    results: Vec<(usize, &str)>,
     regex: &Option<Regex>,
-) -> Result<(), ApplicationError> {
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<ControlFlow<()>, ApplicationError> {
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return Ok(ControlFlow::Continue(())),
+    };
+
+    let query = CompiledQuery::new(&config.query, config.ignore_case, regex.clone());
+    if query.is_match(&name) == config.invert_match {
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    stats.files_with_matches += 1;
+
+    if config.quiet {
+        return Ok(ControlFlow::Break(()));
+    }
+
+    let terminator = if config.null_terminated { '\0' } else { '\n' };
+    write!(writer, "{}{}", path.display(), terminator).map_err(ApplicationError::IOError)?;
+
+    if config.print_first_match {
+        Ok(ControlFlow::Break(()))
+    } else {
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// Records `path` by its canonical form so a file reachable from several roots, e.g. `-R src
+/// src/io`, is only searched once.
+fn is_first_visit(path: &Path, stats: &mut SearchStats) -> bool {
+    match fs::canonicalize(path) {
+        Ok(canonical) => stats.visited_files.insert(canonical),
+        Err(_) => true,
+    }
+}
+
+fn has_multiple_sources(config: &Config) -> bool {
+    config.file_paths.len() > 1 || config.recursive_search || config.auto_recursive
+}
+
+fn report_warning<E: Write>(config: &Config, err_writer: &mut E, error: &ApplicationError) {
+    if !config.suppress_errors {
+        let _ = writeln!(err_writer, "{}", error);
+    }
+}
+
+fn search_content<'a>(
+    config: &Config,
+    content: &'a str,
+    patterns: &SearchPatterns,
+    stats: &SearchStats,
+) -> Result<Vec<LineMatch<'a>>, ApplicationError> {
+    // A leading BOM is not part of the first line, but byte offsets still count it so that
+    // `-b` reports positions in the file as stored.
+    let bom_len = if content.starts_with(UTF8_BOM) {
+        UTF8_BOM.len_utf8()
+    } else {
+        0
+    };
+    let content = &content[bom_len..];
+    let mut results = find_matches(config, content, patterns, stats)?;
+
+    if let Some(order) = config.sort_matches {
+        sort_matches(&mut results, order);
+    }
+
+    if config.before_context > 0 || config.after_context > 0 {
+        let context = if config.context_first_only {
+            with_first_context
+        } else {
+            with_context
+        };
+
+        results = context(
+            content,
+            results,
+            config.before_context,
+            config.after_context,
+        );
+    }
+
+    for result in &mut results {
+        result.byte_offset += bom_len;
+    }
+
+    Ok(results)
+}
+
+fn find_matches<'a>(
+    config: &Config,
+    content: &'a str,
+    patterns: &SearchPatterns,
+    stats: &SearchStats,
+) -> Result<Vec<LineMatch<'a>>, ApplicationError> {
+    let regex = &patterns.regex;
+    let query = CompiledQuery::new(&config.query, config.ignore_case, regex.clone())
+        .with_fuzzy(config.fuzzy)
+        .with_field(config.field, config.delimiter.unwrap_or(','));
+    // `-q` only needs to know whether a file matches, so it can stop at the first match.
+    let max_count = if config.quiet {
+        Some(1)
+    } else {
+        config.max_count
+    };
+    let filters_results = config.min_match_len.is_some()
+        || config.reverse
+        || config.invert_match
+        || config.multi_match_only
+        || config.section_name.is_some();
+    let mut results = match regex {
+        _ if config.slurp => search_slurp(&query, content),
+        Some(regex) if config.multiline => search_multiline(content, regex, config.range),
+        _ => search_until(
+            &query,
+            content,
+            config.range,
+            max_count.filter(|_| !filters_results),
+            stats.deadline,
+            stats.interrupted,
+        )?,
+    };
+
+    if let (Some(regex), Some(min_len)) = (regex, config.min_match_len) {
+        results.retain(|result| {
+            regex
+                .find_iter(result.line)
+                .any(|mat| mat.as_str().chars().count() >= min_len)
+        });
+    }
+
+    if config.multi_match_only {
+        results.retain(|result| result.spans.len() >= 2);
+    }
+
+    if config.invert_match {
+        results = invert_matches(content, &results, config.range);
+    }
+
+    if let (Some(delimiter), Some(name)) = (&patterns.section, &config.section_name) {
+        let sections = section_lines(content, delimiter, name);
+        results.retain(|result| {
+            sections
+                .iter()
+                .any(|section| section.contains(&result.line_number))
+        });
+    }
+
+    if config.reverse {
+        results.reverse();
+    }
+
+    if let Some(max_count) = config.max_count {
+        results.truncate(max_count);
+    }
+
+    if config.passthrough && !results.is_empty() {
+        // Every line is printed, but only the matches count as matches; the rest are context.
+        let mut matches: HashMap<usize, LineMatch> = results
+            .into_iter()
+            .map(|result| (result.line_number, result))
+            .collect();
+        let lines = search_until(
+            &CompiledQuery::new("", false, None),
+            content,
+            config.range,
+            None,
+            stats.deadline,
+            stats.interrupted,
+        )?;
+
+        return Ok(lines
+            .into_iter()
+            .map(|line| {
+                matches.remove(&line.line_number).unwrap_or(LineMatch {
+                    is_context: true,
+                    ..line
+                })
+            })
+            .collect());
+    }
+
+    let mut results = match (regex, config.extract_group, &config.extract_name) {
+        (Some(regex), Some(group), _) => {
+            extract_captures(results, regex, |captures| captures.get(group))
+        }
+        (Some(regex), None, Some(name)) => {
+            extract_captures(results, regex, |captures| captures.name(name))
+        }
+        _ => return Ok(results),
+    };
+
+    if let Some(min_len) = config.min_match_len {
+        results.retain(|result| result.line.chars().count() >= min_len);
+    }
+
+    if config.first_per_line {
+        results.dedup_by_key(|result| result.line_number);
+    }
+
+    Ok(results)
+}
+
+fn extract_captures<'a, F>(
+    results: Vec<LineMatch<'a>>,
+    regex: &Regex,
+    select: F,
+) -> Vec<LineMatch<'a>>
+where
+    F: for<'h> Fn(&Captures<'h>) -> Option<Match<'h>>,
+{
+    results
+        .into_iter()
+        .flat_map(|result| {
+            regex
+                .captures_iter(result.line)
+                .filter_map(|captures| select(&captures))
+                .map(|mat| LineMatch {
+                    line_number: result.line_number,
+                    line: mat.as_str(),
+                    byte_offset: result.byte_offset + mat.start(),
+                    spans: std::iter::once(0..mat.len()).collect(),
+                    is_context: false,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn is_symlink(path: &std::path::Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|meta| meta.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+fn is_hidden(path: &std::path::Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+fn is_skipped(path: &std::path::Path, skip_files: &Option<Regex>) -> bool {
+    match (skip_files, path.file_name()) {
+        (Some(skip_files), Some(name)) => skip_files.is_match(&name.to_string_lossy()),
+        _ => false,
+    }
+}
+
+fn sort_paths(paths: &mut [PathBuf], sort_by: SortBy) {
+    match sort_by {
+        SortBy::Path => paths.sort(),
+        SortBy::Modified => paths.sort_by_cached_key(|path| {
+            (
+                fs::metadata(path).and_then(|meta| meta.modified()).ok(),
+                path.clone(),
+            )
+        }),
+        SortBy::Size => paths.sort_by_cached_key(|path| {
+            (fs::metadata(path).map(|meta| meta.len()).ok(), path.clone())
+        }),
+    }
+}
+
+fn sort_matches(results: &mut [LineMatch], order: MatchOrder) {
+    match order {
+        MatchOrder::Text => results.sort_by(|a, b| a.line.cmp(b.line)),
+        MatchOrder::Numeric => results.sort_by_cached_key(|result| leading_integer(result.line)),
+    }
+}
+
+fn leading_integer(line: &str) -> Option<i64> {
+    let line = line.trim_start();
+    let sign = usize::from(line.starts_with('-'));
+    let digits = line[sign..]
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(line.len() - sign);
+
+    line[..sign + digits].parse().ok()
+}
+
+fn print_count<W: Write>(
+    config: &Config,
+    source: &str,
+    results: &[LineMatch],
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<ControlFlow<()>, ApplicationError> {
+    let matches = results.iter().filter(|result| !result.is_context);
+    let count = if config.count_distinct {
+        matches
+            .map(|result| result.line)
+            .collect::<HashSet<_>>()
+            .len()
+    } else {
+        matches.count()
+    };
+
+    if count > 0 {
+        stats.files_with_matches += 1;
+    } else if config.count_nonzero || config.files_with_count {
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    if config.files_with_count_sorted {
+        stats.match_counts.push((source.to_string(), count));
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    let separator = config.match_separator.as_deref().unwrap_or(":");
+    writeln!(writer, "{}{}{}", source, separator, count).map_err(ApplicationError::IOError)?;
+
+    Ok(ControlFlow::Continue(()))
+}
+
+fn print_results<W: Write>(
+    config: &Config,
+    source: &str,
+    heading: bool,
+    results: Vec<LineMatch>,
+    patterns: &SearchPatterns,
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<ControlFlow<()>, ApplicationError> {
+    let regex = &patterns.regex;
+    let query = CompiledQuery::new(&config.query, config.ignore_case, regex.clone());
+    if (config.count || config.count_distinct || config.files_with_count)
+        && !config.quiet
+        && !config.print_first_match
+    {
+        return print_count(config, source, &results, writer, stats);
+    }
+
+    if config.files_without_match {
+        if results.iter().all(|result| result.is_context) {
+            stats.files_without_match += 1;
+
+            if config.quiet {
+                return Ok(ControlFlow::Break(()));
+            }
+
+            let terminator = if config.null_terminated { '\0' } else { '\n' };
+            write!(writer, "{}{}", source, terminator).map_err(ApplicationError::IOError)?;
+        } else {
+            stats.files_with_matches += 1;
+        }
+
+        return Ok(ControlFlow::Continue(()));
+    }
+
     if results.is_empty() {
-        return Ok(());
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    if config.quiet {
+        stats.files_with_matches += 1;
+        return Ok(ControlFlow::Break(()));
+    }
+
+    if config.json_array {
+        stats.files_with_matches += 1;
+
+        for result in results.iter().filter(|result| !result.is_context) {
+            if results_exhausted(config, stats) {
+                return Ok(ControlFlow::Break(()));
+            }
+
+            writeln!(writer, "{}", match_record(source, result))
+                .map_err(ApplicationError::IOError)?;
+            stats.record_line(result);
+        }
+
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    if config.print_first_match {
+        let terminator = if config.null_terminated { '\0' } else { '\n' };
+        write!(writer, "{}{}", source, terminator).map_err(ApplicationError::IOError)?;
+        stats.files_with_matches += 1;
+        return Ok(ControlFlow::Break(()));
+    }
+
+    if let (Some(separator), true) = (&config.output_separator, stats.files_with_matches > 0) {
+        writeln!(writer, "{}", separator).map_err(ApplicationError::IOError)?;
+    }
+
+    let follows_other_file = stats.files_with_matches > 0;
+
+    if heading {
+        if follows_other_file {
+            writeln!(writer).map_err(ApplicationError::IOError)?;
+        }
+
+        let heading_line = if config.enable_highlighting {
+            highlight_heading(source)
+        } else {
+            source.to_string()
+        };
+
+        writeln!(writer, "{}", heading_line).map_err(ApplicationError::IOError)?;
+    }
+
+    stats.files_with_matches += 1;
+
+    if config.top.is_some() {
+        let count = results.iter().filter(|result| !result.is_context).count();
+        stats.match_counts.push((source.to_string(), count));
     }
 
-    for (line_num, line) in results {
-        let highlighted_line = if config.enable_highlighting {
-            highlight_match(&config.query, line, config.ignore_case, &regex)
+    let show_context = config.before_context > 0 || config.after_context > 0;
+    let match_separator = config.match_separator.as_deref().unwrap_or(":");
+    let context_separator = config.context_separator.as_deref().unwrap_or("-");
+    let mut seen = HashSet::new();
+    let mut last_line = None;
+    let mut last_match = None;
+    let number_width = if config.align_numbers {
+        results
+            .iter()
+            .map(|result| result.line_number.to_string().len())
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let match_lines: Vec<usize> = if config.relative_numbers {
+        results
+            .iter()
+            .filter(|result| !result.is_context)
+            .map(|result| result.line_number)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for result in results {
+        if !result.is_context && config.unique && !seen.insert(result.line) {
+            continue;
+        }
+
+        // Blank context that continues a group still counts as printed for the `--` separators,
+        // so dropping it doesn't split the group in two.
+        if result.is_context && config.trim_context_blank && result.line.trim().is_empty() {
+            if last_line.is_some_and(|last| result.line_number == last + 1) {
+                last_line = Some(result.line_number);
+            }
+            continue;
+        }
+
+        // Past the cap only the last match's after-context is printed, not the next match's
+        // before-context or its `--` separator.
+        let after_last_match = result.is_context
+            && last_match.is_some_and(|last| result.line_number <= last + config.after_context);
+        if results_exhausted(config, stats) && !after_last_match {
+            return Ok(ControlFlow::Break(()));
+        }
+
+        if config.line_numbers_only {
+            if result.is_context {
+                continue;
+            }
+
+            stats.record_line(&result);
+
+            if heading || !has_multiple_sources(config) {
+                writeln!(writer, "{}", result.line_number)
+            } else {
+                writeln!(
+                    writer,
+                    "{}{}{}",
+                    source, match_separator, result.line_number
+                )
+            }
+            .map_err(ApplicationError::IOError)?;
+            continue;
+        }
+
+        if let (true, Some(replacement)) = (config.replace_dry_run, &config.replace) {
+            if !result.is_context {
+                let label = if heading || !has_multiple_sources(config) {
+                    result.line_number.to_string()
+                } else {
+                    format!("{}{}{}", source, match_separator, result.line_number)
+                };
+                print_replacement(&label, result.line, replacement, config, regex, writer)?;
+                stats.record_line(&result);
+            }
+            continue;
+        }
+
+        let replaced = config
+            .replace
+            .as_ref()
+            .map(|replacement| replace_line(result.line, replacement, config, regex));
+        let line = replaced.as_deref().unwrap_or(result.line);
+
+        let line = if config.trim { line.trim_start() } else { line };
+        let line = match config.tab_size {
+            Some(tab_size) => expand_tabs(line, tab_size),
+            None => Cow::Borrowed(line),
+        };
+        let line = match (config.binary_text_preview, &line) {
+            (true, text) => match escape_control_chars(text) {
+                Cow::Owned(escaped) => Cow::Owned(escaped),
+                Cow::Borrowed(_) => line,
+            },
+            (false, _) => line,
+        };
+
+        let match_column = config
+            .max_columns
+            .and_then(|max_columns| preview_column(config, &line, &query, max_columns));
+
+        let highlighted_line = if replaced.is_some() {
+            line.into_owned()
+        } else if config.enable_highlighting && config.dim_context {
+            dim_non_matches(&query, &line)
+        } else if config.enable_highlighting
+            && config.field.is_some()
+            && matches!(line, Cow::Borrowed(_))
+        {
+            let trimmed = result.line.len() - line.len();
+            let spans = result
+                .spans
+                .iter()
+                .filter(|span| span.start >= trimmed)
+                .map(|span| span.start - trimmed..span.end - trimmed)
+                .collect();
+            highlight_spans(&line, spans, config.highlight_style)
+        } else if config.enable_highlighting && !patterns.each_pattern.is_empty() {
+            highlight_patterns(&patterns.each_pattern, &line, config.highlight_style)
+        } else if config.enable_highlighting {
+            highlight_match(
+                &config.query,
+                &line,
+                config.ignore_case,
+                regex,
+                config.highlight_style,
+            )
+        } else {
+            line.into_owned()
+        };
+
+        let highlighted_line = match (config.max_columns, match_column) {
+            (Some(max_columns), Some(column)) => {
+                preview_columns(&highlighted_line, column, max_columns)
+            }
+            (Some(max_columns), None) => {
+                truncate_columns(&highlighted_line, max_columns).into_owned()
+            }
+            (None, _) => highlighted_line,
+        };
+        let highlighted_line = if result.is_context && config.indent_context > 0 {
+            format!("{}{}", " ".repeat(config.indent_context), highlighted_line)
+        } else {
+            highlighted_line
+        };
+
+        let starts_group = match last_line {
+            Some(last) => result.line_number > last + 1,
+            None => follows_other_file && !heading && config.output_separator.is_none(),
+        };
+        if show_context && starts_group {
+            writeln!(writer, "{}", GROUP_SEPARATOR).map_err(ApplicationError::IOError)?;
+        }
+        last_line = Some(result.line_number + result.line.matches('\n').count());
+
+        let separator = if result.is_context {
+            context_separator
         } else {
-            line.to_string()
+            match_separator
         };
+        let mut numbers = String::new();
 
         if config.show_line_numbers {
-            println!("{}:{}: {}", source, line_num, highlighted_line);
+            let line_number = if config.relative_numbers && result.is_context {
+                relative_line_number(result.line_number, &match_lines)
+            } else {
+                result.line_number.to_string()
+            };
+            numbers.push_str(&format!(
+                "{:>width$}{}",
+                line_number,
+                separator,
+                width = number_width
+            ));
+        }
+
+        if config.show_byte_offset {
+            numbers.push_str(&format!("{}{}", result.byte_offset, separator));
+        }
+
+        if !numbers.is_empty() {
+            numbers.push(' ');
+        }
+
+        let output = if heading {
+            format!("{}{}", numbers, highlighted_line)
+        } else {
+            format!("{}{}{}{}", source, separator, numbers, highlighted_line)
+        };
+        let output = if config.global_numbers && !result.is_context {
+            format!("#{} {}", stats.lines_printed + 1, output)
         } else {
-            println!("{}:{}", source, highlighted_line);
+            output
+        };
+
+        writeln!(writer, "{}", output).map_err(ApplicationError::IOError)?;
+
+        if !result.is_context {
+            last_match = last_line;
+            stats.record_line(&result);
         }
     }
-    Ok(())
+
+    if results_exhausted(config, stats) {
+        Ok(ControlFlow::Break(()))
+    } else {
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// Renders a context line's number as its offset from the nearest match in `match_lines`, e.g.
+/// `-1` for the line above a match and `+2` for two lines below one, for `--relative-numbers`.
+/// A line halfway between two matches is counted from the one above.
+fn relative_line_number(line_number: usize, match_lines: &[usize]) -> String {
+    let next = match_lines.partition_point(|&line| line < line_number);
+    let below = next
+        .checked_sub(1)
+        .map(|index| line_number - match_lines[index]);
+    let above = match_lines.get(next).map(|line| line - line_number);
+
+    match (below, above) {
+        (Some(below), Some(above)) if above < below => format!("-{}", above),
+        (Some(below), _) => format!("+{}", below),
+        (None, Some(above)) => format!("-{}", above),
+        (None, None) => line_number.to_string(),
+    }
+}
+
+/// Prints `line` before and after the replacement for `--replace-dry-run`, coloring the removed
+/// and added text when highlighting is on. Lines the replacement leaves unchanged are skipped.
+fn print_replacement<W: Write>(
+    label: &str,
+    line: &str,
+    replacement: &str,
+    config: &Config,
+    regex: &Option<Regex>,
+    writer: &mut W,
+) -> Result<(), ApplicationError> {
+    let (replaced, removed, added) = replace_line_with_spans(line, replacement, config, regex);
+
+    if replaced == line {
+        return Ok(());
+    }
+
+    let (before, after) = if config.enable_highlighting {
+        (
+            highlight_removed(line, removed),
+            highlight_added(&replaced, added),
+        )
+    } else {
+        (line.to_string(), replaced)
+    };
+
+    writeln!(writer, "{}\n- {}\n+ {}", label, before, after).map_err(ApplicationError::IOError)
+}
+
+/// The column of the first match in `line` when `--preview-match` applies, i.e. when the match
+/// does not end within the first `max_columns` characters.
+fn preview_column(
+    config: &Config,
+    line: &str,
+    query: &CompiledQuery,
+    max_columns: usize,
+) -> Option<usize> {
+    if !config.preview_match {
+        return None;
+    }
+
+    let span = query.spans(line).into_iter().next()?;
+
+    (line[..span.end].chars().count() > max_columns).then(|| line[..span.start].chars().count())
+}
+
+fn results_exhausted(config: &Config, stats: &SearchStats) -> bool {
+    config
+        .max_results
+        .is_some_and(|max_results| stats.lines_printed >= max_results)
+}
+
+fn expand_tabs(line: &str, tab_size: usize) -> Cow<'_, str> {
+    if !line.contains('\t') {
+        return Cow::Borrowed(line);
+    }
+
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_size - column % tab_size;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+
+    Cow::Owned(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{regex::compile_regex, stats::Progress, test_util::temp_dir};
+    use std::{cell::RefCell, path::Path, rc::Rc};
+
+    fn line_match(line_number: usize, byte_offset: usize, line: &str) -> LineMatch<'_> {
+        LineMatch {
+            line_number,
+            line,
+            byte_offset,
+            spans: Vec::new(),
+            is_context: false,
+        }
+    }
+
+    fn extracted(line_number: usize, byte_offset: usize, line: &str) -> LineMatch<'_> {
+        LineMatch {
+            spans: std::iter::once(0..line.len()).collect(),
+            ..line_match(line_number, byte_offset, line)
+        }
+    }
+
+    fn heading_config() -> Config {
+        Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            heading: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_read_patterns() {
+        let dir = temp_dir("read-patterns");
+        let path = dir.join("patterns.txt");
+        fs::write(&path, "^error\nwarn\n").unwrap();
+
+        let patterns = read_patterns(path.to_str().unwrap(), Some('#')).unwrap();
+        assert_eq!(patterns, vec!["^error".to_string(), "warn".to_string()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_patterns_skips_comments_and_blank_lines() {
+        let dir = temp_dir("read-patterns-comments");
+        let path = dir.join("patterns.txt");
+        fs::write(
+            &path,
+            "# errors\n^error\n\n   \n\\#include\n; warnings\nwarn\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        assert_eq!(
+            read_patterns(path, Some('#')).unwrap(),
+            vec![
+                "^error".to_string(),
+                "#include".to_string(),
+                "; warnings".to_string(),
+                "warn".to_string()
+            ]
+        );
+        assert_eq!(
+            read_patterns(path, Some(';')).unwrap(),
+            vec![
+                "# errors".to_string(),
+                "^error".to_string(),
+                "\\#include".to_string(),
+                "warn".to_string()
+            ]
+        );
+        assert_eq!(
+            read_patterns(path, None).unwrap(),
+            vec![
+                "# errors".to_string(),
+                "^error".to_string(),
+                "\\#include".to_string(),
+                "; warnings".to_string(),
+                "warn".to_string()
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_print_results_heading_once() {
+        let config = heading_config();
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let results = vec![
+            line_match(1, 0, "rust is fast"),
+            line_match(3, 20, "rust is safe"),
+        ];
+        let _ = print_results(
+            &config,
+            "poem.txt",
+            true,
+            results,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "poem.txt\n1: rust is fast\n3: rust is safe\n");
+        assert_eq!(output.matches("poem.txt").count(), 1);
+    }
+
+    #[test]
+    fn test_print_results_heading_blank_line_between_files() {
+        let config = heading_config();
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for source in ["a.txt", "b.txt"] {
+            let results = vec![line_match(2, 5, "rust")];
+            let _ = print_results(
+                &config,
+                source,
+                true,
+                results,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "a.txt\n2: rust\n\nb.txt\n2: rust\n");
+    }
+
+    #[test]
+    fn test_print_results_context_separator_between_files() {
+        let config = Config {
+            file_paths: vec!["a.txt".to_string(), "b.txt".to_string()],
+            before_context: 1,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for source in ["a.txt", "b.txt"] {
+            let results = vec![
+                LineMatch {
+                    is_context: true,
+                    ..line_match(1, 0, "go")
+                },
+                line_match(2, 3, "rust"),
+            ];
+            let _ = print_results(
+                &config,
+                source,
+                false,
+                results,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("a.txt-go\na.txt:rust\n{GROUP_SEPARATOR}\nb.txt-go\nb.txt:rust\n")
+        );
+    }
+
+    #[test]
+    fn test_print_results_heading_highlighted() {
+        let config = Config {
+            enable_highlighting: true,
+            ..heading_config()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = print_results(
+            &config,
+            "poem.txt",
+            true,
+            vec![line_match(1, 0, "rust")],
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("\x1b[1;35mpoem.txt\x1b[0m\n"));
+    }
+
+    #[test]
+    fn test_print_results_trim() {
+        let config = Config {
+            query: "rust".to_string(),
+            trim: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let results = vec![
+            line_match(1, 0, "    rust is fast"),
+            line_match(2, 17, "\t\trust  is safe  "),
+        ];
+        let _ = print_results(
+            &config,
+            "poem.txt",
+            false,
+            results,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "poem.txt:rust is fast\npoem.txt:rust  is safe  \n");
+    }
+
+    #[test]
+    fn test_process_input_trim_matches_full_line() {
+        let config = Config {
+            query: "  rust".to_string(),
+            trim: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "    rust\nrust\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "stdin:rust\n");
+    }
+
+    #[test]
+    fn test_process_input_max_columns() {
+        let config = Config {
+            query: "needle".to_string(),
+            max_columns: Some(8),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let line = format!("{}needle", "x".repeat(40));
+        let content = format!("{}\nneedle\n", line);
+        let mut input = content.as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "stdin:xxxxxxxx [... +38 chars]\nstdin:needle\n");
+    }
+
+    #[test]
+    fn test_process_input_preview_match() {
+        let config = Config {
+            query: "needle".to_string(),
+            max_columns: Some(8),
+            preview_match: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let content = format!(
+            "{}needle{}\nneedle{}\n",
+            "x".repeat(40),
+            "y".repeat(10),
+            "z".repeat(10)
+        );
+        let mut input = content.as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "stdin:[match at col 41] needleyy [... +8 chars]\nstdin:needlezz [... +8 chars]\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_field() {
+        let config = Config {
+            query: "error".to_string(),
+            show_line_numbers: true,
+            field: Some(3),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "time,host,level,message\n\
+                         1,web,error,disk full\n\
+                         2,error-host,info,ok\n\
+                         3,db,info,error in message\n\
+                         4,db,error\n\
+                         5,error\n"
+            .as_bytes();
+
+        let _ = process_input(
+            "log",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "log:2: 1,web,error,disk full\nlog:5: 4,db,error\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_field_highlights_only_the_field() {
+        let config = Config {
+            query: "a".to_string(),
+            field: Some(2),
+            delimiter: Some(';'),
+            trim: true,
+            enable_highlighting: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "  a;ba;a\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:a;b\x1b[1;33ma\x1b[0m;a\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_align_numbers() {
+        let input: String = (1..=120)
+            .map(|n| if n % 50 == 5 { "rust\n" } else { "go\n" })
+            .collect();
+
+        for (align_numbers, expected) in [
+            (false, "stdin:5: rust\nstdin:55: rust\nstdin:105: rust\n"),
+            (true, "stdin:  5: rust\nstdin: 55: rust\nstdin:105: rust\n"),
+        ] {
+            let config = Config {
+                query: "rust".to_string(),
+                show_line_numbers: true,
+                align_numbers,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_input(
+                "stdin",
+                &mut input.as_bytes(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn test_process_input_gzip_stdin() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = std::io::Cursor::new(vec![
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x2b, 0x2a, 0x2d, 0x2e,
+            0x51, 0x30, 0xe4, 0x4a, 0xd6, 0xd6, 0xe6, 0x2a, 0x02, 0x31, 0x8d, 0xb8, 0x00, 0xcd,
+            0x0d, 0xbf, 0x1c, 0x12, 0x00, 0x00, 0x00,
+        ]);
+
+        let _ =
+            process_input("stdin", &mut input, &config, &None, &mut output, &mut stats).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:1: rust 1\nstdin:3: rust 2\n"
+        );
+    }
+
+    #[test]
+    fn test_process_file_binary_text_preview() {
+        let dir = temp_dir("binary-text-preview");
+        let path = dir.join("data.bin");
+        fs::write(&path, b"\x00\x01rust\x1b[2J\x07\xff\xc2\x9b\nother\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        for (binary_text_preview, expected) in [
+            (false, "\0\x01rust\x1b[2J\x07\u{fffd}\u{9b}\n"),
+            (true, "\\x00\\x01rust\\x1b[2J\\x07\\xff\\xc2\\x9b\n"),
+        ] {
+            let config = Config {
+                query: "rust".to_string(),
+                binary_as_text: true,
+                binary_text_preview,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_file(
+                path,
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!("{}:{}", path, expected)
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_section() {
+        let config = Config {
+            query: "port".to_string(),
+            show_line_numbers: true,
+            section: Some(r"^\[(.+)\]$".to_string()),
+            section_name: Some("server".to_string()),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let patterns = SearchPatterns {
+            section: Some(Regex::new(r"^\[(.+)\]$").unwrap()),
+            ..Default::default()
+        };
+        let mut stats = SearchStats::default();
+        let mut input = "port = 1\n[client]\nport = 2\n[server]\nhost = a\nport = 3\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &patterns,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "stdin:6: port = 3\n");
+    }
+
+    #[test]
+    fn test_process_input_counts_matched_chars() {
+        let config = Config {
+            query: "caf".to_string(),
+            after_context: 1,
+            count_chars: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "café au lait\ntea\ncafé\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(stats.matched_chars, 16);
+        assert_eq!(stats.matched_bytes, 18);
+    }
+
+    #[test]
+    fn test_process_input_match_histogram() {
+        let config = Config {
+            query: "ab".to_string(),
+            after_context: 1,
+            match_histogram: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "ab\nab ab\nnone\nab\nab ab ab\nab ab\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            stats.histogram_summary(),
+            "1 match: 2 lines, 2 matches: 2 lines, 3 matches: 1 line"
+        );
+    }
+
+    #[test]
+    fn test_process_input_reverse() {
+        let input = "rust 1\nc++\nrust 2\ngo\nrust 3\n";
+
+        for (max_count, expected) in [
+            (None, "stdin:5: rust 3\nstdin:3: rust 2\nstdin:1: rust 1\n"),
+            (Some(1), "stdin:5: rust 3\n"),
+        ] {
+            let config = Config {
+                query: "rust".to_string(),
+                show_line_numbers: true,
+                reverse: true,
+                max_count,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_input(
+                "stdin",
+                &mut input.as_bytes(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_process_input_fuzzy() {
+        let config = Config {
+            query: "receive".to_string(),
+            fuzzy: Some(1),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "recive the data\nreceived\nrecipe\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:recive the data\nstdin:received\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_ignores_heading() {
+        let config = heading_config();
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "rust\nc++\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "stdin:1: rust\n");
+    }
+
+    #[test]
+    fn test_process_directory_sorted_by_path() {
+        let dir = temp_dir("sort-path");
+        fs::create_dir(dir.join("b")).unwrap();
+        fs::write(dir.join("c.txt"), "rust c\n").unwrap();
+        fs::write(dir.join("a.txt"), "rust a\n").unwrap();
+        fs::write(dir.join("b").join("z.txt"), "rust z\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+        for output in [&mut first, &mut second] {
+            let mut stats = SearchStats::default();
+            let _ = process_directory(
+                &dir,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        let first = String::from_utf8(first).unwrap();
+        let lines: Vec<&str> = first.lines().collect();
+        let expected: Vec<String> = [("a.txt", "a"), ("b/z.txt", "z"), ("c.txt", "c")]
+            .iter()
+            .map(|(file, word)| format!("{}:rust {}", dir.join(Path::new(file)).display(), word))
+            .collect();
+
+        assert_eq!(lines, expected);
+        assert_eq!(first, String::from_utf8(second).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_global_numbers() {
+        let dir = temp_dir("global-numbers");
+        let first = dir.join("a.txt");
+        let second = dir.join("b.txt");
+        fs::write(&first, "rust 1\nc++\nrust 2\n").unwrap();
+        fs::write(&second, "rust 3\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            global_numbers: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for path in [&first, &second] {
+            let _ = process_file(
+                path.to_str().unwrap(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "#1 {0}:1: rust 1\n#2 {0}:3: rust 2\n#3 {1}:1: rust 3\n",
+                first.display(),
+                second.display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_output_separator() {
+        let dir = temp_dir("output-separator");
+        let paths = [dir.join("a.txt"), dir.join("b.txt"), dir.join("c.txt")];
+        fs::write(&paths[0], "rust 1\nrust 2\n").unwrap();
+        fs::write(&paths[1], "c++\n").unwrap();
+        fs::write(&paths[2], "rust 3\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            output_separator: Some("%%".to_string()),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for path in &paths {
+            let _ = process_file(
+                path.to_str().unwrap(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("%%").count(), 1);
+        assert_eq!(
+            output,
+            format!(
+                "{0}:rust 1\n{0}:rust 2\n%%\n{1}:rust 3\n",
+                paths[0].display(),
+                paths[2].display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sort_paths_by_size() {
+        let dir = temp_dir("sort-size");
+        fs::write(dir.join("big.txt"), "rust rust rust\n").unwrap();
+        fs::write(dir.join("small.txt"), "rust\n").unwrap();
+
+        let mut paths = vec![dir.join("big.txt"), dir.join("small.txt")];
+        sort_paths(&mut paths, SortBy::Size);
+
+        assert_eq!(paths, vec![dir.join("small.txt"), dir.join("big.txt")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_skip_files() {
+        let dir = temp_dir("skip-files");
+        fs::write(dir.join("app.log"), "rust log\n").unwrap();
+        fs::write(dir.join("notes.txt"), "rust notes\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+        let skip_files = Some(Regex::new(r"\.log$").unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &skip_files,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            format!("{}:rust notes\n", dir.join("notes.txt").display())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_unrestricted_levels() {
+        let dir = temp_dir("unrestricted");
+        fs::write(dir.join("notes.txt"), "rust notes\n").unwrap();
+        fs::write(dir.join(".hidden"), "rust secret\n").unwrap();
+        fs::write(dir.join("data.bin"), b"rust \xff\xfe\n").unwrap();
+
+        for (flag, expected) in [
+            ("-u", vec!["notes.txt"]),
+            ("-uu", vec![".hidden", "notes.txt"]),
+            ("-uuu", vec![".hidden", "data.bin", "notes.txt"]),
+        ] {
+            let args = ["minigrep", "-s", "--sort=path", flag, "rust"].map(String::from);
+            let config = Config::build(args.into_iter()).unwrap();
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_directory(
+                &dir,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            let output = String::from_utf8(output).unwrap();
+            let names: Vec<&str> = output
+                .lines()
+                .filter_map(|line| line.split(':').next())
+                .filter_map(|path| Path::new(path).file_name()?.to_str())
+                .collect();
+            assert_eq!(names, expected, "{}", flag);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_skips_hidden() {
+        let dir = temp_dir("hidden");
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join(".git").join("config"), "rust remote\n").unwrap();
+        fs::write(dir.join(".hidden"), "rust secret\n").unwrap();
+        fs::write(dir.join("notes.txt"), "rust notes\n").unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust notes\n", dir.join("notes.txt").display())
+        );
+
+        config.hidden = true;
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}:rust remote\n{}:rust secret\n{}:rust notes\n",
+                dir.join(".git").join("config").display(),
+                dir.join(".hidden").display(),
+                dir.join("notes.txt").display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_counts_searched_files() {
+        let dir = temp_dir("count-files");
+        fs::create_dir(dir.join("nested")).unwrap();
+        fs::write(dir.join("app.log"), "rust log\n").unwrap();
+        fs::write(dir.join("notes.txt"), "rust notes\n").unwrap();
+        fs::write(dir.join("empty.txt"), "").unwrap();
+        fs::write(dir.join("nested").join("lib.rs"), "no match\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+        let skip_files = Some(Regex::new(r"\.log$").unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &skip_files,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(stats.files_searched, 3);
+        assert_eq!(stats.files_with_matches, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_quiet_stops_at_first_match() {
+        let dir = temp_dir("quiet");
+        fs::write(dir.join("a.txt"), "rust\n").unwrap();
+        fs::write(dir.join("b.txt"), "rust\n".repeat(100_000)).unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            quiet: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let flow = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert!(flow.is_break());
+        assert!(output.is_empty());
+        assert_eq!(stats.files_searched, 1);
+        assert_eq!(stats.files_with_matches, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_quiet_without_match_continues() {
+        let config = Config {
+            query: "rust".to_string(),
+            quiet: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "c++\n".as_bytes();
+
+        let flow = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert!(flow.is_continue());
+        assert_eq!(stats.files_with_matches, 0);
+    }
+
+    #[test]
+    fn test_process_input_byte_offsets() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            show_byte_offset: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "rust\nçà\nrust\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "stdin:1:0: rust\nstdin:3:10: rust\n");
+    }
+
+    #[test]
+    fn test_process_input_strips_bom() {
+        let config = Config {
+            query: "^rust$".to_string(),
+            use_regex: true,
+            show_line_numbers: true,
+            show_byte_offset: true,
+            ..Default::default()
+        };
+        let regex = compile_regex(&config.query, &config).unwrap();
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "\u{FEFF}rust\nc++\nrust\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "stdin:1:3: rust\nstdin:3:12: rust\n");
+    }
+
+    #[test]
+    fn test_process_file_strips_bom() {
+        let dir = temp_dir("bom");
+        let path = dir.join("bom.txt");
+        fs::write(&path, b"\xEF\xBB\xBFfoo = 1\nbar = foo\n").unwrap();
+        let path = path.to_str().unwrap();
+        let config = Config {
+            query: "^foo".to_string(),
+            use_regex: true,
+            ..Default::default()
+        };
+        let regex = compile_regex(&config.query, &config).unwrap();
+        let mut output = Vec::new();
+
+        let _ = process_file(
+            path,
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut SearchStats::default(),
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, format!("{}:foo = 1\n", path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_head_bytes() {
+        let dir = temp_dir("head-bytes");
+        let path = dir.join("log.txt");
+        fs::write(&path, "#!/bin/sh rust\nfiller\nrust later\n").unwrap();
+        let path = path.to_str().unwrap();
+        let config = Config {
+            query: "rust".to_string(),
+            head_bytes: Some(16),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        let _ = process_file(
+            path,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut SearchStats::default(),
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, format!("{}:#!/bin/sh rust\n", path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_complete_utf8_len() {
+        assert_eq!(complete_utf8_len(b"abc"), 3);
+        assert_eq!(complete_utf8_len("aé".as_bytes()), 3);
+        assert_eq!(complete_utf8_len(&"aé".as_bytes()[..2]), 1);
+        assert_eq!(complete_utf8_len(&"a😀".as_bytes()[..4]), 1);
+        assert_eq!(complete_utf8_len(b""), 0);
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("\trust", 4), "    rust");
+        assert_eq!(expand_tabs("ab\tc\td", 4), "ab  c   d");
+        assert_eq!(expand_tabs("no tabs", 4), "no tabs");
+    }
+
+    #[test]
+    fn test_process_input_tabsize_matches_original_line() {
+        let config = Config {
+            query: "\trust".to_string(),
+            tab_size: Some(8),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "\trust\n    rust\n".as_bytes();
+
+        let _ = process_input(
+            "stdin",
+            &mut input,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "stdin:        rust\n");
+    }
+
+    #[test]
+    fn test_process_directory_print_first_match() {
+        let dir = temp_dir("print-first-match");
+        fs::write(dir.join("a.txt"), "c++\n").unwrap();
+        fs::write(dir.join("b.txt"), "rust\nrust\n").unwrap();
+        fs::write(dir.join("c.txt"), "rust\n").unwrap();
+
+        for (null_terminated, terminator) in [(false, "\n"), (true, "\0")] {
+            let config = Config {
+                query: "rust".to_string(),
+                print_first_match: true,
+                null_terminated,
+                sort_by: Some(SortBy::Path),
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let flow = process_directory(
+                &dir,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert!(flow.is_break());
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!("{}{}", dir.join("b.txt").display(), terminator)
+            );
+            assert_eq!(stats.files_searched, 2);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_past_deadline() {
+        let config = Config {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats {
+            deadline: Some(std::time::Instant::now()),
+            ..Default::default()
+        };
+        let input = "rust\n".repeat(10_000);
+
+        let result = process_input(
+            "stdin",
+            &mut input.as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        );
+
+        assert!(matches!(result, Err(ApplicationError::Timeout)));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_process_directory_continues_past_unreadable_file() {
+        let dir = temp_dir("unreadable");
+        fs::write(dir.join("a.bin"), [0xff, 0xfe, b'r', b'u', b's', b't']).unwrap();
+        fs::write(dir.join("b.txt"), "rust\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            suppress_errors: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust\n", dir.join("b.txt").display())
+        );
+        assert_eq!(stats.files_with_matches, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_exit_on_error() {
+        let dir = temp_dir("exit-on-error");
+        fs::write(dir.join("a.bin"), [0xff, 0xfe, b'r', b'u', b's', b't']).unwrap();
+        fs::write(dir.join("b.txt"), "rust\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            exit_on_error: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let result = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        );
+
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidEncoding(ref path)) if path.ends_with("a.bin"))
+        );
+        assert!(output.is_empty());
+        assert_eq!(stats.files_searched, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_name_pattern() {
+        let dir = temp_dir("name-pattern");
+        fs::create_dir(dir.join("sites")).unwrap();
+        fs::write(dir.join("app.conf"), "listen 80\n").unwrap();
+        fs::write(dir.join("sites").join("api.conf"), "listen 8080\n").unwrap();
+        fs::write(dir.join("notes.txt"), "listen here\n").unwrap();
+
+        let config = Config {
+            query: "listen".to_string(),
+            name_pattern: Some("*.conf".to_string()),
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}:listen 80\n{}:listen 8080\n",
+                dir.join("app.conf").display(),
+                dir.join("sites").join("api.conf").display()
+            )
+        );
+        assert_eq!(stats.files_searched, 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_file_age() {
+        let dir = temp_dir("file-age");
+        let day = Duration::from_secs(24 * 60 * 60);
+        for (name, age) in [
+            ("today.log", 0),
+            ("last-week.log", 7),
+            ("last-year.log", 365),
+        ] {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            (&file).write_all(b"error\n").unwrap();
+            file.set_modified(SystemTime::now() - day * age).unwrap();
+        }
+
+        let search = |newer_than, older_than| {
+            let config = Config {
+                query: "error".to_string(),
+                sort_by: Some(SortBy::Path),
+                newer_than,
+                older_than,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let _ = process_directory(
+                &dir,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                &mut output,
+                &mut SearchStats::default(),
+            )
+            .unwrap();
+
+            String::from_utf8(output)
+                .unwrap()
+                .lines()
+                .map(|line| {
+                    line.trim_start_matches(&format!("{}/", dir.display()))
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search(Some(day), None), vec!["today.log:error"]);
+        assert_eq!(search(None, Some(day * 30)), vec!["last-year.log:error"]);
+        assert_eq!(
+            search(Some(day * 30), Some(day)),
+            vec!["last-week.log:error"]
+        );
+        assert_eq!(search(None, None).len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_process_directory_reports_progress() {
+        let dir = temp_dir("progress");
+        fs::write(dir.join("a.txt"), "rust\n").unwrap();
+        fs::write(dir.join("b.txt"), "c++\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let progress_output = Rc::new(RefCell::new(Vec::new()));
+        let mut stats = SearchStats {
+            progress: Some(Progress::new(
+                Box::new(SharedBuffer(Rc::clone(&progress_output))),
+                Duration::ZERO,
+            )),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(progress_output.take()).unwrap(),
+            format!(
+                "\r\x1b[K1 files searched: {}\r\x1b[K2 files searched: {}",
+                dir.join("a.txt").display(),
+                dir.join("b.txt").display()
+            )
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust\n", dir.join("a.txt").display())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_invalid_utf8() {
+        let dir = temp_dir("invalid-utf8");
+        let path = dir.join("image.bin");
+        fs::write(&path, [b'r', b'u', b's', b't', 0xff, b'\n']).unwrap();
+        let path = path.to_str().unwrap();
+        let config = Config {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+
+        let result = process_file(
+            path,
+            &config,
+            &SearchPatterns::default(),
+            &mut Vec::new(),
+            &mut SearchStats::default(),
+        );
+
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidEncoding(ref file)) if file == path),
+            "Expected Err(ApplicationError::InvalidEncoding), got {:?}",
+            result
+        );
+
+        let result = process_file(
+            dir.join("missing.txt").to_str().unwrap(),
+            &config,
+            &SearchPatterns::default(),
+            &mut Vec::new(),
+            &mut SearchStats::default(),
+        );
+        assert!(matches!(result, Err(ApplicationError::FileNotFound(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_invalid_utf8() {
+        let config = Config {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+
+        let result = process_input(
+            "stdin",
+            &mut [0xff, b'\n'].as_slice(),
+            &config,
+            &SearchPatterns::default(),
+            &mut Vec::new(),
+            &mut SearchStats::default(),
+        );
+
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidEncoding(ref source)) if source == "stdin")
+        );
+    }
+
+    #[test]
+    fn test_process_file_rejects_directory() {
+        let dir = temp_dir("is-a-directory");
+        let path = dir.display().to_string();
+        let config = Config {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let result = process_file(
+            &path,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        );
+
+        assert!(
+            matches!(result, Err(ApplicationError::IsADirectory(ref s)) if *s == path),
+            "Expected Err(ApplicationError::IsADirectory), got {:?}",
+            result
+        );
+        assert_eq!(stats.files_searched, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_file_with_preprocessor() {
+        let dir = temp_dir("pre");
+        let path = dir.join("input.txt").display().to_string();
+        fs::write(&path, "rust\nc++\n").unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            preprocess_cmd: Some("cat".to_string()),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_file(
+            &path,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust\n", path)
+        );
+        assert_eq!(stats.files_searched, 1);
+
+        config.preprocess_cmd = Some("false".to_string());
+        let result = process_file(
+            &path,
+            &config,
+            &SearchPatterns::default(),
+            &mut Vec::new(),
+            &mut stats,
+        );
+        assert!(
+            matches!(result, Err(ApplicationError::PreprocessorFailed(ref s)) if *s == format!("false {}", path)),
+            "Expected Err(ApplicationError::PreprocessorFailed), got {:?}",
+            result
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_with_preprocessor_heading() {
+        let dir = temp_dir("pre-heading");
+        let path = dir.join("input.txt").display().to_string();
+        fs::write(&path, "rust\nc++\n").unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            heading: true,
+            ..Default::default()
+        };
+        let mut expected = Vec::new();
+        let _ = process_file(
+            &path,
+            &config,
+            &SearchPatterns::default(),
+            &mut expected,
+            &mut SearchStats::default(),
+        )
+        .unwrap();
+
+        config.preprocess_cmd = Some("cat".to_string());
+        let mut output = Vec::new();
+        let _ = process_file(
+            &path,
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut SearchStats::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output, expected);
+        assert!(String::from_utf8(output).unwrap().starts_with(&path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_matches_quiet_stops_at_first_match() {
+        let config = Config {
+            query: "rust".to_string(),
+            quiet: true,
+            ..Default::default()
+        };
+
+        let results = find_matches(
+            &config,
+            "rust 1\nrust 2\nrust 3\n",
+            &SearchPatterns::default(),
+            &SearchStats::default(),
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].line, "rust 1");
+    }
+
+    #[cfg(unix)]
+    extern "C" fn ignore_signal(_: std::ffi::c_int) {}
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_input_interrupted_while_blocked() {
+        use std::{
+            os::unix::thread::JoinHandleExt, sync::atomic::AtomicBool, thread, time::Instant,
+        };
+        static INTERRUPTED: AtomicBool = AtomicBool::new(true);
+
+        // SIGUSR1 stands in for Ctrl-C so the process-wide SIGINT handler and flag are left
+        // alone; like it, the handler is installed without `SA_RESTART`.
+        // SAFETY: the action is fully initialised and the handler does nothing.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            let handler: extern "C" fn(std::ffi::c_int) = ignore_signal;
+            action.sa_sigaction = handler as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            assert_eq!(
+                libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()),
+                0
+            );
+        }
+
+        // The write end stays open, so the read blocks until the signal cuts it short.
+        let (mut reader, _writer) = io::pipe().unwrap();
+        let reading = thread::spawn(move || {
+            let stats = SearchStats {
+                interrupted: Some(&INTERRUPTED),
+                ..Default::default()
+            };
+            read_input(&mut reader, &stats)
+        });
+        let started = Instant::now();
+
+        while !reading.is_finished() && started.elapsed() < Duration::from_secs(5) {
+            // SAFETY: the thread hasn't been joined, so its handle is still valid.
+            unsafe { libc::pthread_kill(reading.as_pthread_t(), libc::SIGUSR1) };
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(reading.is_finished(), "read was not interrupted");
+        assert!(matches!(
+            reading.join().unwrap(),
+            Err(ApplicationError::Interrupted)
+        ));
+    }
+
+    #[test]
+    fn test_process_input_passthrough() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            enable_highlighting: true,
+            passthrough: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "c++\nrust\ngo\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-1- c++\nstdin:2: \x1b[1;33mrust\x1b[0m\nstdin-3- go\n"
+        );
+        assert_eq!(stats.files_with_matches, 1);
+    }
+
+    #[test]
+    fn test_process_input_passthrough_counts_only_matches() {
+        let config = Config {
+            query: "rust".to_string(),
+            passthrough: true,
+            count: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "c++\nrust\ngo\nrust\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "stdin:2\n");
+    }
+
+    #[test]
+    fn test_process_input_max_results_stops_context() {
+        let config = Config {
+            query: "rust".to_string(),
+            before_context: 1,
+            after_context: 1,
+            max_results: Some(1),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "rust 1\nc++\ngo\njava\nrust 2\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:rust 1\nstdin-c++\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_passthrough_max_results() {
+        let config = Config {
+            query: "rust".to_string(),
+            passthrough: true,
+            max_results: Some(1),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "rust 1\nc++\nrust 2\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("rust 1"));
+        assert!(!output.contains("rust 2"));
+        assert_eq!(stats.lines_printed, 1);
+    }
+
+    #[test]
+    fn test_process_input_passthrough_dim_context() {
+        let config = Config {
+            query: "rust".to_string(),
+            enable_highlighting: true,
+            passthrough: true,
+            dim_context: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "c++\nrust code\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-\x1b[2mc++\x1b[0m\nstdin:rust\x1b[2m code\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_passthrough_without_match() {
+        let config = Config {
+            query: "rust".to_string(),
+            passthrough: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "c++\ngo\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert!(output.is_empty());
+        assert_eq!(stats.files_with_matches, 0);
+    }
+
+    #[test]
+    fn test_process_directory_path_styles() {
+        let dir = temp_dir("path-style");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("a.txt"), "rust\n").unwrap();
+        let canonical = fs::canonicalize(&dir).unwrap();
+
+        for (path_style, expected) in [
+            (
+                Some(PathStyle::Absolute),
+                canonical.join("sub").join("a.txt").display().to_string(),
+            ),
+            (
+                Some(PathStyle::Relative(dir.display().to_string())),
+                PathBuf::from("sub").join("a.txt").display().to_string(),
+            ),
+            (None, dir.join("sub").join("a.txt").display().to_string()),
+        ] {
+            let config = Config {
+                query: "rust".to_string(),
+                path_style,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_directory(
+                &dir,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!("{}:rust\n", expected)
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_display_path_outside_base() {
+        let dir = temp_dir("path-outside");
+        let file = dir.join("a.txt");
+        fs::write(&file, "rust\n").unwrap();
+        let file = file.display().to_string();
+
+        let config = Config {
+            path_style: Some(PathStyle::Relative(
+                dir.join("missing").display().to_string(),
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(display_path(&file, &config), file);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_min_match_len() {
+        let config = Config {
+            query: r"\w+".to_string(),
+            use_regex: true,
+            min_match_len: Some(4),
+            ..Default::default()
+        };
+        let regex = Some(Regex::new(&config.query).unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a b c\nrust is\nok go\n".as_bytes(),
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "stdin:rust is\n");
+    }
+
+    #[test]
+    fn test_process_input_min_match_len_filters_extracted_matches() {
+        let config = Config {
+            query: r"(\w+)".to_string(),
+            use_regex: true,
+            extract_group: Some(1),
+            min_match_len: Some(4),
+            ..Default::default()
+        };
+        let regex = Some(Regex::new(&config.query).unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "rust is fast\n".as_bytes(),
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:rust\nstdin:fast\n"
+        );
+    }
+
+    #[test]
+    fn test_process_directory_first_only() {
+        let dir = temp_dir("first-only");
+        fs::write(dir.join("a.txt"), "rust 1\nc++\nrust 2\nrust 3\n").unwrap();
+        fs::write(dir.join("b.txt"), "go\nrust 4\nrust 5\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            sort_by: Some(SortBy::Path),
+            max_count: Some(1),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}:1: rust 1\n{}:2: rust 4\n",
+                dir.join("a.txt").display(),
+                dir.join("b.txt").display()
+            )
+        );
+        assert_eq!(stats.files_with_matches, 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_name_only() {
+        let dir = temp_dir("name-only");
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src").join("Main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("notes.txt"), "main\n").unwrap();
+        fs::write(dir.join("readme.md"), "main\n").unwrap();
+
+        let mut config = Config {
+            query: "main".to_string(),
+            ignore_case: true,
+            name_only: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}\n", dir.join("src").join("Main.rs").display())
+        );
+        assert_eq!(stats.files_with_matches, 1);
+        assert_eq!(stats.files_searched, 0);
+
+        config.use_regex = true;
+        config.query = r"\.(md|txt)$".to_string();
+        let regex = Some(Regex::new(&config.query).unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::from(regex),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}\n{}\n",
+                dir.join("notes.txt").display(),
+                dir.join("readme.md").display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_sort_matches() {
+        let dir = temp_dir("sort-matches");
+        let path = dir.join("sizes.txt");
+        fs::write(
+            &path,
+            "10 kb pear
+skip
+9 kb apple
+-3 kb fig
+100 kb banana
+",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        for (order, expected) in [
+            (
+                MatchOrder::Text,
+                "-3 kb fig
+10 kb pear
+100 kb banana
+9 kb apple
+",
+            ),
+            (
+                MatchOrder::Numeric,
+                "-3 kb fig
+9 kb apple
+10 kb pear
+100 kb banana
+",
+            ),
+        ] {
+            let config = Config {
+                query: "kb".to_string(),
+                sort_matches: Some(order),
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+
+            let _ = process_file(
+                path,
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut SearchStats::default(),
+            )
+            .unwrap();
+
+            let output = String::from_utf8(output).unwrap();
+            assert_eq!(output.replace(&format!("{}:", path), ""), expected);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_leading_integer() {
+        assert_eq!(leading_integer("42 apples"), Some(42));
+        assert_eq!(leading_integer("  -7: below zero"), Some(-7));
+        assert_eq!(leading_integer("apples"), None);
+        assert_eq!(leading_integer("-"), None);
+    }
+
+    #[test]
+    fn test_process_input_count_and_count_distinct() {
+        let input = "error: disk\nok\nerror: disk\nerror: net\nerror: disk\n";
+
+        for (count_distinct, expected) in [(false, "stdin:4\n"), (true, "stdin:2\n")] {
+            let config = Config {
+                query: "error".to_string(),
+                count: true,
+                count_distinct,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_input(
+                "stdin",
+                &mut input.as_bytes(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+            assert_eq!(stats.files_with_matches, 1);
+        }
+    }
+
+    #[test]
+    fn test_process_directory_count_nonzero() {
+        let dir = temp_dir("count-nonzero");
+        fs::write(dir.join("a.txt"), "rust\nrust\n").unwrap();
+        fs::write(dir.join("b.txt"), "c++\n").unwrap();
+        fs::write(dir.join("c.txt"), "go\nrust\n").unwrap();
+
+        for (count_nonzero, expected) in [
+            (false, vec![("a.txt", 2), ("b.txt", 0), ("c.txt", 1)]),
+            (true, vec![("a.txt", 2), ("c.txt", 1)]),
+        ] {
+            let config = Config {
+                query: "rust".to_string(),
+                count: true,
+                count_nonzero,
+                sort_by: Some(SortBy::Path),
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_directory(
+                &dir,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            let expected: String = expected
+                .iter()
+                .map(|(name, count)| format!("{}:{}\n", dir.join(name).display(), count))
+                .collect();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+            assert_eq!(stats.files_with_matches, 2);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_files_with_count() {
+        let dir = temp_dir("files-with-count");
+        fs::write(dir.join("a.txt"), "rust\n").unwrap();
+        fs::write(dir.join("b.txt"), "c++\n").unwrap();
+        fs::write(dir.join("c.txt"), "rust\ngo\nrust\n").unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            files_with_count: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}:1\n{}:2\n",
+                dir.join("a.txt").display(),
+                dir.join("c.txt").display()
+            )
+        );
+        assert_eq!(stats.files_with_matches, 2);
+
+        config.files_with_count_sorted = true;
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert!(output.is_empty());
+        assert_eq!(
+            stats.top_files(usize::MAX),
+            vec![
+                (dir.join("c.txt").to_str().unwrap(), 2),
+                (dir.join("a.txt").to_str().unwrap(), 1)
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_files_without_match_quiet() {
+        let dir = temp_dir("files-without-match-quiet");
+        fs::write(dir.join("a.txt"), "c++\n").unwrap();
+        fs::write(dir.join("b.txt"), "go\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            files_without_match: true,
+            quiet: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert!(output.is_empty());
+        assert_eq!(stats.files_without_match, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_files_without_match() {
+        let dir = temp_dir("files-without-match");
+        fs::write(dir.join("empty.txt"), "").unwrap();
+        fs::write(dir.join("match.txt"), "rust\n").unwrap();
+        fs::write(dir.join("other.txt"), "c++\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            files_without_match: true,
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}\n{}\n",
+                dir.join("empty.txt").display(),
+                dir.join("other.txt").display()
+            )
+        );
+        assert_eq!(stats.files_searched, 3);
+        assert_eq!(stats.files_without_match, 2);
+        assert_eq!(stats.files_with_matches, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_empty_file_count() {
+        let dir = temp_dir("empty-count");
+        let path = dir.join("empty.txt");
+        fs::write(&path, "").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            count: true,
+            head_bytes: Some(16),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_file(
+            path.to_str().unwrap(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:0\n", path.display())
+        );
+        assert_eq!(stats.files_searched, 1);
+        assert_eq!(stats.files_with_matches, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_count_without_match() {
+        let config = Config {
+            query: "rust".to_string(),
+            count_distinct: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "c++\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "stdin:0\n");
+        assert_eq!(stats.files_with_matches, 0);
+    }
+
+    #[test]
+    fn test_process_input_multi_match_only() {
+        let config = Config {
+            query: r"\d+".to_string(),
+            use_regex: true,
+            show_line_numbers: true,
+            multi_match_only: true,
+            ..Default::default()
+        };
+        let regex = compile_regex(&config.query, &config).unwrap();
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "no digits\nport 80\nfrom 8080 to 443\n".as_bytes(),
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:3: from 8080 to 443\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_unique() {
+        let config = Config {
+            query: "error".to_string(),
+            show_line_numbers: true,
+            unique: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "error: disk\nok\nerror: disk\nerror: net\nerror: disk\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:1: error: disk\nstdin:4: error: net\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_replace() {
+        let config = Config {
+            query: "foo".to_string(),
+            replace: Some("bar".to_string()),
+            enable_highlighting: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "foo one\nkeep\nfoo foo\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin:bar one\nstdin:bar bar\n"
+        );
+    }
+
+    #[test]
+    fn test_process_file_replace_dry_run() {
+        let dir = temp_dir("replace-dry-run");
+        let path = dir.join("main.rs");
+        let content = "use foo;\nfn main() {}\nfoo::run(foo);\n";
+        fs::write(&path, content).unwrap();
+
+        for (enable_highlighting, expected) in [
+            (
+                false,
+                "1\n- use foo;\n+ use bar;\n3\n- foo::run(foo);\n+ bar::run(bar);\n",
+            ),
+            (
+                true,
+                "1\n- use \x1b[1;31mfoo\x1b[0m;\n+ use \x1b[1;32mbar\x1b[0m;\n3\n\
+                 - \x1b[1;31mfoo\x1b[0m::run(\x1b[1;31mfoo\x1b[0m);\n\
+                 + \x1b[1;32mbar\x1b[0m::run(\x1b[1;32mbar\x1b[0m);\n",
+            ),
+        ] {
+            let config = Config {
+                query: "foo".to_string(),
+                replace: Some("bar".to_string()),
+                replace_dry_run: true,
+                enable_highlighting,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_file(
+                path.to_str().unwrap(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_replace_in_place() {
+        let dir = temp_dir("replace-in-place");
+        fs::write(dir.join("a.txt"), "foo\nkeep\n").unwrap();
+        fs::write(dir.join("b.txt"), "keep\n").unwrap();
+
+        let config = Config {
+            query: "f(o+)".to_string(),
+            use_regex: true,
+            replace: Some("b${1}p".to_string()),
+            in_place: true,
+            ..Default::default()
+        };
+        let regex = Some(Regex::new(&config.query).unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::from(regex),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert!(output.is_empty());
+        assert_eq!(stats.files_searched, 2);
+        assert_eq!(stats.files_with_matches, 1);
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "boop\nkeep\n"
+        );
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "keep\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_collects_match_counts_for_top() {
+        let dir = temp_dir("top");
+        let files = [("a.log", 2), ("b.log", 5), ("c.log", 1), ("d.log", 5)];
+        for (name, count) in files {
+            fs::write(dir.join(name), "error\n".repeat(count)).unwrap();
+        }
+
+        let config = Config {
+            query: "error".to_string(),
+            file_paths: files
+                .iter()
+                .map(|(name, _)| dir.join(name).display().to_string())
+                .collect(),
+            top: Some(3),
+            ..Default::default()
+        };
+        let mut stats = SearchStats::default();
+
+        for path in &config.file_paths {
+            let _ = process_file(
+                path,
+                &config,
+                &SearchPatterns::default(),
+                &mut Vec::new(),
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        let path = |name: &str| dir.join(name).display().to_string();
+        let top: Vec<(String, usize)> = stats
+            .top_files(3)
+            .into_iter()
+            .map(|(path, count)| (path.to_string(), count))
+            .collect();
+        assert_eq!(
+            top,
+            vec![(path("b.log"), 5), (path("d.log"), 5), (path("a.log"), 2)]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_line_numbers_only() {
+        let config = Config {
+            query: "rust".to_string(),
+            line_numbers_only: true,
+            range: Some((2, 5)),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "rust\nc++\nrust\ngo\nrust\nrust\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "3\n5\n");
+    }
+
+    #[test]
+    fn test_process_directory_line_numbers_only_prefixes_path() {
+        let dir = temp_dir("line-numbers-only");
+        fs::write(dir.join("a.txt"), "c++\nrust\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            recursive_search: true,
+            line_numbers_only: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:2\n", dir.join("a.txt").display())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_slurp() {
+        let dir = temp_dir("slurp");
+        let path = dir.join("data.xml");
+        fs::write(&path, "<item>\n  <name>rust</name>\n</item>\n<item/>\n").unwrap();
+        let path = path.display().to_string();
+
+        let config = Config {
+            query: r"<item>.*?</item>".to_string(),
+            use_regex: true,
+            slurp: true,
+            show_byte_offset: true,
+            ..Default::default()
+        };
+        let regex = compile_regex(&config.query, &config).unwrap();
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_file(
+            &path,
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:0: <item>\n  <name>rust</name>\n</item>\n", path)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_file_types() {
+        let dir = temp_dir("file-types");
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src").join("main.rs"), "rust\n").unwrap();
+        fs::write(dir.join("setup.py"), "rust\n").unwrap();
+        fs::write(dir.join("notes.txt"), "rust\n").unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            sort_by: Some(SortBy::Path),
+            file_types: vec!["rust".to_string()],
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust\n", dir.join("src").join("main.rs").display())
+        );
+        assert_eq!(stats.files_searched, 1);
+
+        config.file_types.clear();
+        config.excluded_types = vec!["rust".to_string(), "txt".to_string()];
+        let mut output = Vec::new();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust\n", dir.join("setup.py").display())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_input_context() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            before_context: 1,
+            after_context: 1,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a\nrust 1\nb\nc\nd\nrust 2\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-1- a\nstdin:2: rust 1\nstdin-3- b\n--\nstdin-5- d\nstdin:6: rust 2\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_custom_separators() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            before_context: 1,
+            match_separator: Some("=".to_string()),
+            context_separator: Some("~".to_string()),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a\nrust 1\nb\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin~1~ a\nstdin=2= rust 1\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_context_first_only() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            before_context: 1,
+            after_context: 1,
+            context_first_only: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a\nrust 1\nb\nc\nrust 2\nd\nrust 3\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-1- a\nstdin:2: rust 1\nstdin-3- b\n--\nstdin:5: rust 2\n--\nstdin:7: rust 3\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_trim_context_blank() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            before_context: 2,
+            after_context: 2,
+            trim_context_blank: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a\n  \nrust 1\n\nb\n\n\n\nrust 2\n\t\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-1- a\nstdin:3: rust 1\nstdin-5- b\n--\nstdin:9: rust 2\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_relative_numbers() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            before_context: 2,
+            after_context: 2,
+            relative_numbers: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a\nb\nc\nrust\nd\ne\nf\ng\nrust\nh\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin--2- b\nstdin--1- c\nstdin:4: rust\nstdin-+1- d\nstdin-+2- e\n\
+             stdin--2- f\nstdin--1- g\nstdin:9: rust\nstdin-+1- h\n"
+        );
+    }
+
+    #[test]
+    fn test_relative_line_number() {
+        let match_lines = [5, 9];
+
+        assert_eq!(relative_line_number(3, &match_lines), "-2");
+        assert_eq!(relative_line_number(6, &match_lines), "+1");
+        assert_eq!(relative_line_number(7, &match_lines), "+2");
+        assert_eq!(relative_line_number(8, &match_lines), "-1");
+        assert_eq!(relative_line_number(11, &match_lines), "+2");
+    }
+
+    #[test]
+    fn test_process_input_indent_context() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            before_context: 1,
+            after_context: 1,
+            indent_context: 4,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "a\nrust\nb\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-1-     a\nstdin:2: rust\nstdin-3-     b\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_invert_match_with_context() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            invert_match: true,
+            before_context: 1,
+            after_context: 1,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for (source, input) in [
+            (
+                "stdin",
+                "rust 1\nrust 2\nok 3\nrust 4\nrust 5\nrust 6\nok 7\n",
+            ),
+            ("other", "rust 1\nok 2\n"),
+        ] {
+            let _ = process_input(
+                source,
+                &mut input.as_bytes(),
+                &config,
+                &SearchPatterns::default(),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "stdin-2- rust 2\nstdin:3: ok 3\nstdin-4- rust 4\n{GROUP_SEPARATOR}\n\
+                 stdin-6- rust 6\nstdin:7: ok 7\n{GROUP_SEPARATOR}\nother-1- rust 1\nother:2: ok 2\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_process_input_invert_match_first_only() {
+        let config = Config {
+            query: "rust".to_string(),
+            invert_match: true,
+            max_count: Some(1),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "rust 1\nok 2\nok 3\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "stdin:ok 2\n");
+    }
+
+    #[test]
+    fn test_process_input_range_with_context() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            range: Some((3, 5)),
+            before_context: 1,
+            after_context: 1,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_input(
+            "stdin",
+            &mut "rust 1\nb\nrust 3\nd\nrust 5\nf\nrust 7\n".as_bytes(),
+            &config,
+            &SearchPatterns::default(),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stdin-2- b\nstdin:3: rust 3\nstdin-4- d\nstdin:5: rust 5\nstdin-6- f\n"
+        );
+    }
+
+    #[test]
+    fn test_process_directory_max_results() {
+        let dir = temp_dir("max-results");
+        fs::write(dir.join("a.txt"), "rust 1\nrust 2\n").unwrap();
+        fs::write(dir.join("b.txt"), "rust 3\nc++\nrust 4\n").unwrap();
+        fs::write(dir.join("c.txt"), "rust 5\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            sort_by: Some(SortBy::Path),
+            max_results: Some(3),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let flow = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(flow.is_break());
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.ends_with("rust 3\n"));
+        assert_eq!(stats.lines_printed, 3);
+        assert_eq!(stats.files_searched, 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_warning() {
+        let error = ApplicationError::FileNotFound("a.bin".to_string());
+
+        let mut err_output = Vec::new();
+        report_warning(&Config::default(), &mut err_output, &error);
+        assert_eq!(
+            String::from_utf8(err_output).unwrap(),
+            "Error: File 'a.bin' not found.\n"
+        );
+
+        let config = Config {
+            suppress_errors: true,
+            ..Default::default()
+        };
+        let mut err_output = Vec::new();
+        report_warning(&config, &mut err_output, &error);
+        assert!(err_output.is_empty());
+    }
+
+    #[test]
+    fn test_process_input_extract_group() {
+        let config = Config {
+            query: r"user=(\w+)".to_string(),
+            use_regex: true,
+            extract_group: Some(1),
+            show_line_numbers: true,
+            ..Default::default()
+        };
+        let regex = Some(Regex::new(&config.query).unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "user=alice id=1 user=bob\nno users here\nuser=carol\n".as_bytes();
+
+        let _ = process_input(
+            "log",
+            &mut input,
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "log:1: alice\nlog:1: bob\nlog:3: carol\n"
+        );
+    }
+
+    #[test]
+    fn test_process_input_extract_first_per_line() {
+        let input = "id=1 id=1 id=1\nnone\nid=2\n";
+
+        for (first_per_line, expected) in [
+            (false, "log:1: 1\nlog:1: 1\nlog:1: 1\nlog:3: 2\n"),
+            (true, "log:1: 1\nlog:3: 2\n"),
+        ] {
+            let config = Config {
+                query: r"id=(\d+)".to_string(),
+                use_regex: true,
+                extract_group: Some(1),
+                first_per_line,
+                show_line_numbers: true,
+                ..Default::default()
+            };
+            let regex = Some(Regex::new(&config.query).unwrap());
+            let mut output = Vec::new();
+            let mut stats = SearchStats::default();
+
+            let _ = process_input(
+                "log",
+                &mut input.as_bytes(),
+                &config,
+                &SearchPatterns::from(regex),
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_extract_captures_skips_missing_group() {
+        let regex = Regex::new(r"id=(\d+)|name=\w+").unwrap();
+        let results = vec![
+            line_match(1, 0, "id=42"),
+            line_match(2, 6, "name=rust"),
+            line_match(3, 16, "x id=7"),
+        ];
+
+        assert_eq!(
+            extract_captures(results, &regex, |captures| captures.get(1)),
+            vec![extracted(1, 3, "42"), extracted(3, 21, "7")]
+        );
+    }
+
+    #[test]
+    fn test_process_input_extract_name() {
+        let config = Config {
+            query: r"(?P<user>\w+)@(?P<host>[\w.]+)".to_string(),
+            use_regex: true,
+            extract_name: Some("host".to_string()),
+            ..Default::default()
+        };
+        let regex = Some(Regex::new(&config.query).unwrap());
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let mut input = "alice@example.com\nnobody\nbob@rustlang.org carol@crates.io\n".as_bytes();
+
+        let _ = process_input(
+            "log",
+            &mut input,
+            &config,
+            &SearchPatterns::from(regex),
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "log:example.com\nlog:rustlang.org\nlog:crates.io\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_captures_skips_missing_name() {
+        let regex = Regex::new(r"id=(?P<id>\d+)|name=\w+").unwrap();
+        let results = vec![line_match(1, 0, "name=rust"), line_match(2, 10, "id=7")];
+
+        assert_eq!(
+            extract_captures(results, &regex, |captures| captures.name("id")),
+            vec![extracted(2, 13, "7")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_directory_symlinked_directory() {
+        let dir = temp_dir("follow");
+        let target = temp_dir("follow-target");
+        fs::write(target.join("linked.txt"), "rust linked\n").unwrap();
+        fs::write(dir.join("local.txt"), "rust local\n").unwrap();
+        std::os::unix::fs::symlink(&target, dir.join("link")).unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            sort_by: Some(SortBy::Path),
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust local\n", dir.join("local.txt").display())
+        );
+
+        config.follow_links = true;
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}:rust linked\n{}:rust local\n",
+                dir.join("link").join("linked.txt").display(),
+                dir.join("local.txt").display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    fn test_process_directory_overlapping_roots_search_each_file_once() {
+        let dir = temp_dir("overlapping-roots");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("top.txt"), "rust top\n").unwrap();
+        fs::write(dir.join("sub").join("a.txt"), "rust sub\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            recursive_search: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        for root in [dir.join("sub"), dir.clone()] {
+            let _ = process_directory(
+                &root,
+                &config,
+                &SearchPatterns::default(),
+                &None,
+                &mut output,
+                &mut stats,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(stats.files_searched, 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "{}:rust sub\n{}:rust top\n",
+                dir.join("sub").join("a.txt").display(),
+                dir.join("top.txt").display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_directory_symlink_cycle_terminates() {
+        let dir = temp_dir("follow-cycle");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("a.txt"), "rust\n").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("back")).unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            follow_links: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats::default();
+
+        let _ = process_directory(
+            &dir,
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut output,
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(stats.files_searched, 1);
+        assert_eq!(stats.visited_dirs.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,142 @@
+use crate::search::LineMatch;
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+/// Quotes `text` as a JSON string.
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Formats a matching line as a JSON object for `--json-array`.
+pub fn match_record(source: &str, result: &LineMatch) -> String {
+    let submatches: Vec<String> = result
+        .spans
+        .iter()
+        .map(|span| format!("{{\"start\":{},\"end\":{}}}", span.start, span.end))
+        .collect();
+
+    format!(
+        "{{\"path\":{},\"line_number\":{},\"byte_offset\":{},\"line\":{},\"submatches\":[{}]}}",
+        json_string(source),
+        result.line_number,
+        result.byte_offset,
+        json_string(result.line),
+        submatches.join(",")
+    )
+}
+
+/// Wraps the buffered records in a single JSON array, one record per line.
+pub fn json_array(records: &[String]) -> String {
+    if records.is_empty() {
+        return "[]".to_string();
+    }
+
+    format!("[\n{}\n]", records.join(",\n"))
+}
+
+/// Collects the records written for `--json-array`, one per line, and writes them to the inner
+/// writer as a single array once every source has been searched.
+pub struct JsonSink<W: Write> {
+    inner: W,
+    records: Vec<u8>,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(inner: W) -> Self {
+        JsonSink {
+            inner,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        let records = String::from_utf8_lossy(&self.records);
+        let records: Vec<String> = records.lines().map(String::from).collect();
+        writeln!(self.inner, "{}", json_array(&records))
+    }
+}
+
+impl<W: Write> Write for JsonSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.records.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("say \"hi\"\\path\n\ttab\u{1}"),
+            "\"say \\\"hi\\\"\\\\path\\n\\ttab\\u0001\""
+        );
+        assert_eq!(json_string("café"), "\"café\"");
+    }
+
+    #[test]
+    fn test_match_record() {
+        let result = LineMatch {
+            line_number: 3,
+            line: "a \"rust\" b",
+            byte_offset: 12,
+            spans: std::iter::once(3..7).collect(),
+            is_context: false,
+        };
+
+        assert_eq!(
+            match_record("src/a.rs", &result),
+            "{\"path\":\"src/a.rs\",\"line_number\":3,\"byte_offset\":12,\
+             \"line\":\"a \\\"rust\\\" b\",\"submatches\":[{\"start\":3,\"end\":7}]}"
+        );
+    }
+
+    #[test]
+    fn test_json_array() {
+        assert_eq!(json_array(&[]), "[]");
+        assert_eq!(
+            json_array(&["{\"a\":1}".to_string(), "{\"b\":2}".to_string()]),
+            "[\n{\"a\":1},\n{\"b\":2}\n]"
+        );
+    }
+    #[test]
+    fn test_json_sink() {
+        let mut output = Vec::new();
+        let mut sink = JsonSink::new(&mut output);
+        writeln!(sink, "{{\"a\":1}}").unwrap();
+        writeln!(sink, "{{\"b\":2}}").unwrap();
+        sink.finish().unwrap();
+        assert_eq!(output, b"[\n{\"a\":1},\n{\"b\":2}\n]\n");
+
+        let mut output = Vec::new();
+        JsonSink::new(&mut output).finish().unwrap();
+        assert_eq!(output, b"[]\n");
+    }
+}
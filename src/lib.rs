@@ -1,11 +1,45 @@
-pub mod config;
-pub mod error;
-pub mod highlight;
-pub mod io;
-pub mod regex;
-pub mod runner;
-pub mod search;
+//! A small grep-like search library and command line tool.
+//!
+//! The crate root re-exports the stable API; the modules behind it are internal and may change.
+//!
+//! ```
+//! use greplite::{compile_regex, search, Config};
+//!
+//! let config = Config {
+//!     query: r"\d+ ms".to_string(),
+//!     use_regex: true,
+//!     ..Default::default()
+//! };
+//! let regex = compile_regex(&config.query, &config)?;
+//! let matches = search(&config.query, "start\nfetched in 42 ms\ndone\n", false, &regex, None);
+//!
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].line_number, 2);
+//! assert_eq!(matches[0].line, "fetched in 42 ms");
+//! # Ok::<(), greplite::ApplicationError>(())
+//! ```
 
-pub use config::Config;
+mod config;
+#[cfg(feature = "decompress")]
+mod decompress;
+mod error;
+mod glob;
+mod highlight;
+mod interrupt;
+mod io;
+mod json;
+mod regex;
+mod replace;
+mod runner;
+mod search;
+mod stats;
+#[cfg(test)]
+mod test_util;
+mod types;
+
+pub use config::{ColorChoice, Config, MatchOrder, PathStyle, SortBy};
 pub use error::ApplicationError;
-pub use runner::run;
+pub use highlight::{highlight_match, HighlightStyle};
+pub use regex::compile_regex;
+pub use runner::{exit_status, run, run_with_io};
+pub use search::{search, search_with_spans, LineMatch};
@@ -7,8 +7,10 @@ fn main() {
         process::exit(1);
     });
 
-    if let Err(error) = greplite::run(config) {
+    let result = greplite::run(config);
+
+    if let Err(error) = &result {
         error.handle_error();
-        process::exit(1);
     }
+    process::exit(greplite::exit_status(&result));
 }
@@ -1,38 +1,184 @@
-use crate::error::ApplicationError;
-use regex::{Regex, RegexBuilder};
+use crate::{config::Config, error::ApplicationError};
+use regex::{Error, Regex, RegexBuilder};
 
-pub fn compile_regex(
-    query: &str,
-    use_regex: bool,
-    ignore_case: bool,
-) -> Result<Option<Regex>, ApplicationError> {
-    if use_regex {
-        let mut builder = RegexBuilder::new(query);
-
-        if ignore_case {
-            builder.case_insensitive(true);
+/// The regexes compiled from the command line once per run, passed next to `&Config` to the
+/// search and output code.
+#[derive(Debug, Default)]
+pub struct SearchPatterns {
+    /// The search regex, or `None` for a plain substring search.
+    pub regex: Option<Regex>,
+    /// Each `-f` pattern compiled on its own, so highlighting can tell which pattern matched.
+    pub each_pattern: Vec<Regex>,
+    /// The `--section` header pattern.
+    pub section: Option<Regex>,
+}
+
+impl From<Option<Regex>> for SearchPatterns {
+    fn from(regex: Option<Regex>) -> Self {
+        SearchPatterns {
+            regex,
+            ..Default::default()
         }
+    }
+}
 
-        builder
-            .build()
-            .map(Some)
-            .map_err(|_| ApplicationError::InvalidRegex(query.to_string()))
+pub fn compile_regex(query: &str, config: &Config) -> Result<Option<Regex>, ApplicationError> {
+    if config.use_regex || config.word_regexp {
+        let pattern = if config.use_regex {
+            word_bounded(query, config)
+        } else {
+            word_bounded(&regex::escape(query), config)
+        };
+        let mut builder = RegexBuilder::new(&pattern);
+        apply_regex_flags(&mut builder, config);
+        build_regex(&mut builder, query, config).map(Some)
     } else {
         Ok(None)
     }
 }
 
+/// A pattern no line can match, for an empty pattern list.
+const MATCH_NOTHING: &str = r"[^\s\S]";
+
+pub fn compile_patterns(patterns: &[String], config: &Config) -> Result<Regex, ApplicationError> {
+    // Joining no patterns would give the empty pattern, which matches every line; grep matches
+    // nothing for an empty pattern file.
+    if patterns.is_empty() {
+        return build_regex(&mut RegexBuilder::new(MATCH_NOTHING), "", config);
+    }
+
+    let alternatives = patterns
+        .iter()
+        .map(|pattern| pattern_alternative(pattern, config))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    build_pattern_set(&alternatives.join("|"), &patterns.join("\n"), config)
+}
+
+/// Compiles each pattern on its own with the same options as `compile_patterns`, so that
+/// highlighting can tell which pattern produced a match.
+pub fn compile_each_pattern(
+    patterns: &[String],
+    config: &Config,
+) -> Result<Vec<Regex>, ApplicationError> {
+    patterns
+        .iter()
+        .map(|pattern| build_pattern_set(&pattern_alternative(pattern, config)?, pattern, config))
+        .collect()
+}
+
+fn pattern_alternative(pattern: &str, config: &Config) -> Result<String, ApplicationError> {
+    if config.use_regex && !config.fixed_strings {
+        build_regex(&mut RegexBuilder::new(pattern), pattern, config)?;
+        Ok(format!("(?:{})", pattern))
+    } else {
+        Ok(format!("(?:{})", regex::escape(pattern)))
+    }
+}
+
+fn build_pattern_set(
+    alternatives: &str,
+    source: &str,
+    config: &Config,
+) -> Result<Regex, ApplicationError> {
+    let query = word_bounded(alternatives, config);
+    let mut builder = RegexBuilder::new(&query);
+    apply_regex_flags(&mut builder, config);
+
+    build_regex(&mut builder, source, config)
+}
+
+/// Wraps `pattern` in word boundaries for `-w`: Unicode-aware `\b` by default, or ASCII-only
+/// `[A-Za-z0-9_]` word characters with `--no-unicode-word`.
+fn word_bounded(pattern: &str, config: &Config) -> String {
+    if !config.word_regexp {
+        return pattern.to_string();
+    }
+
+    let boundary = if config.ascii_word {
+        r"(?-u:\b)"
+    } else {
+        r"\b"
+    };
+    format!("{}(?:{}){}", boundary, pattern, boundary)
+}
+
+/// Applies the options every search regex shares: `-i`, `-U` and `--slurp`, `--no-unicode`, and
+/// `--regex-flags`, which `Config::build` has already limited to `i`, `m`, `s` and `x`.
+fn apply_regex_flags(builder: &mut RegexBuilder, config: &Config) {
+    builder.case_insensitive(config.ignore_case);
+
+    if config.multiline || config.slurp {
+        builder.multi_line(true).dot_matches_new_line(true);
+    }
+
+    if config.no_unicode {
+        builder.unicode(false);
+    }
+
+    for flag in config.regex_flags.iter().flat_map(|flags| flags.chars()) {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            _ => builder,
+        };
+    }
+}
+
+fn build_regex(
+    builder: &mut RegexBuilder,
+    query: &str,
+    config: &Config,
+) -> Result<Regex, ApplicationError> {
+    if let Some(size_limit) = config.regex_size_limit {
+        builder.size_limit(size_limit);
+    }
+
+    if let Some(dfa_size_limit) = config.dfa_size_limit {
+        builder.dfa_size_limit(dfa_size_limit);
+    }
+
+    builder.build().map_err(|error| match error {
+        Error::CompiledTooBig(_) => ApplicationError::RegexTooLarge(query.to_string()),
+        // Without Unicode mode `.` and negated classes match single bytes, which a `&str` regex
+        // refuses to compile; say so rather than calling the pattern invalid.
+        Error::Syntax(message) if config.no_unicode && message.contains("invalid UTF-8") => {
+            ApplicationError::NonUtf8Regex(query.to_string())
+        }
+        _ => ApplicationError::InvalidRegex(query.to_string()),
+    })
+}
+
+pub fn compile_filename_regex(pattern: &Option<String>) -> Result<Option<Regex>, ApplicationError> {
+    pattern
+        .as_ref()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|_| ApplicationError::InvalidRegex(pattern.to_string()))
+        })
+        .transpose()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn regex_config(use_regex: bool, ignore_case: bool) -> Config {
+        Config {
+            use_regex,
+            ignore_case,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_compile_regex_no_regex() {
         let query = "rust";
         let use_regex = false;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(None) => (),
@@ -46,7 +192,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(Some(regex)) => {
@@ -63,7 +209,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Err(ApplicationError::InvalidRegex(ref s)) => {
@@ -82,7 +228,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = true;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(Some(regex)) => {
@@ -94,4 +240,317 @@ mod tests {
             _ => panic!("Expected Ok(Some(regex)), got {:?}", result),
         }
     }
+
+    #[test]
+    fn test_compile_regex_multiline() {
+        let regex = compile_regex(
+            "start.*end",
+            &Config {
+                use_regex: true,
+                multiline: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert!(regex.is_match("start\nend"));
+
+        let regex = compile_regex("start.*end", &regex_config(true, false))
+            .unwrap()
+            .unwrap();
+        assert!(!regex.is_match("start\nend"));
+    }
+
+    #[test]
+    fn test_compile_patterns_multiline() {
+        let patterns = vec!["start.*end".to_string(), "^second".to_string()];
+
+        for config in [
+            Config {
+                use_regex: true,
+                multiline: true,
+                ..Default::default()
+            },
+            Config {
+                use_regex: true,
+                slurp: true,
+                ..Default::default()
+            },
+        ] {
+            let regex = compile_patterns(&patterns, &config).unwrap();
+            assert!(regex.is_match("start\nend"));
+            assert!(regex.is_match("first\nsecond"));
+
+            let regexes = compile_each_pattern(&patterns, &config).unwrap();
+            assert!(regexes[0].is_match("start\nend"));
+        }
+
+        let regex = compile_patterns(&patterns, &regex_config(true, false)).unwrap();
+        assert!(!regex.is_match("start\nend"));
+    }
+
+    #[test]
+    fn test_compile_patterns_empty_matches_nothing() {
+        for config in [regex_config(true, false), regex_config(false, true)] {
+            let regex = compile_patterns(&[], &config).unwrap();
+
+            assert!(!regex.is_match(""));
+            assert!(!regex.is_match("rust"));
+        }
+    }
+
+    #[test]
+    fn test_compile_patterns_matches_any_pattern() {
+        let patterns = vec![r"^error".to_string(), r"\d{3}$".to_string()];
+
+        let regex = compile_patterns(&patterns, &regex_config(true, false)).unwrap();
+
+        assert_eq!(regex.as_str(), r"(?:^error)|(?:\d{3}$)");
+        assert!(regex.is_match("error: disk full"));
+        assert!(regex.is_match("status 404"));
+        assert!(!regex.is_match("warning: disk 90% full"));
+    }
+
+    #[test]
+    fn test_compile_each_pattern() {
+        let patterns = vec!["a.c".to_string(), "XYZ".to_string()];
+        let regexes = compile_each_pattern(&patterns, &regex_config(false, true)).unwrap();
+
+        assert_eq!(regexes.len(), 2);
+        assert!(regexes[0].is_match("a.c") && !regexes[0].is_match("abc"));
+        assert!(regexes[1].is_match("xyz") && !regexes[1].is_match("a.c"));
+    }
+
+    #[test]
+    fn test_compile_patterns_case_insensitive() {
+        let patterns = vec!["rust".to_string(), "go".to_string()];
+
+        let regex = compile_patterns(&patterns, &regex_config(true, true)).unwrap();
+
+        assert!(regex.is_match("RUST"));
+        assert!(regex.is_match("Go"));
+    }
+
+    #[test]
+    fn test_compile_patterns_escapes_literals() {
+        let patterns = vec!["a.b".to_string(), "(c)".to_string()];
+
+        let regex = compile_patterns(&patterns, &regex_config(false, false)).unwrap();
+
+        assert!(regex.is_match("a.b"));
+        assert!(!regex.is_match("axb"));
+        assert!(regex.is_match("(c)"));
+    }
+
+    #[test]
+    fn test_compile_patterns_reports_invalid_pattern() {
+        let patterns = vec!["rust".to_string(), "[go".to_string(), "c++".to_string()];
+
+        let result = compile_patterns(&patterns, &regex_config(true, false));
+
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidRegex(ref s)) if s == "[go"),
+            "Expected Err(ApplicationError::InvalidRegex), got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_regex_size_limit_exceeded() {
+        let config = Config {
+            use_regex: true,
+            regex_size_limit: Some(100),
+            ..Default::default()
+        };
+
+        let result = compile_regex(r"\w{50}", &config);
+
+        assert!(
+            matches!(result, Err(ApplicationError::RegexTooLarge(ref s)) if s == r"\w{50}"),
+            "Expected Err(ApplicationError::RegexTooLarge), got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_regex_within_size_limit() {
+        let config = Config {
+            use_regex: true,
+            regex_size_limit: Some(1024 * 1024),
+            dfa_size_limit: Some(1024 * 1024),
+            ..Default::default()
+        };
+
+        assert!(compile_regex("rust", &config).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_compile_patterns_size_limit_exceeded() {
+        let config = Config {
+            use_regex: true,
+            regex_size_limit: Some(100),
+            ..Default::default()
+        };
+        let patterns = vec!["rust".to_string(), r"\w{50}".to_string()];
+
+        let result = compile_patterns(&patterns, &config);
+
+        assert!(
+            matches!(result, Err(ApplicationError::RegexTooLarge(ref s)) if s == r"\w{50}"),
+            "Expected Err(ApplicationError::RegexTooLarge), got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_filename_regex() {
+        let regex = compile_filename_regex(&Some(r"\.log$".to_string()))
+            .unwrap()
+            .unwrap();
+        assert!(regex.is_match("app.log"));
+        assert!(!regex.is_match("app.txt"));
+
+        assert!(compile_filename_regex(&None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_compile_filename_regex_invalid() {
+        let result = compile_filename_regex(&Some("[log".to_string()));
+        assert!(
+            matches!(result, Err(ApplicationError::InvalidRegex(ref s)) if s == "[log"),
+            "Expected Err(ApplicationError::InvalidRegex), got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_regex_word_regexp() {
+        let config = Config {
+            word_regexp: true,
+            ..Default::default()
+        };
+        let regex = compile_regex("a.b", &config).unwrap().unwrap();
+
+        assert!(regex.is_match("see a.b here"));
+        assert!(!regex.is_match("see xa.b here"));
+        assert!(!regex.is_match("see axb here"));
+    }
+
+    #[test]
+    fn test_compile_regex_word_regexp_unicode_and_ascii() {
+        let unicode = Config {
+            word_regexp: true,
+            ..Default::default()
+        };
+        let ascii = Config {
+            word_regexp: true,
+            ascii_word: true,
+            ..Default::default()
+        };
+        let unicode = compile_regex("caf", &unicode).unwrap().unwrap();
+        let ascii = compile_regex("caf", &ascii).unwrap().unwrap();
+
+        assert!(!unicode.is_match("café au lait"));
+        assert!(ascii.is_match("café au lait"));
+
+        assert!(unicode.is_match("un caf noir"));
+        assert!(ascii.is_match("un caf noir"));
+        assert!(!ascii.is_match("cafe"));
+    }
+
+    #[test]
+    fn test_compile_patterns_word_regexp() {
+        let config = Config {
+            word_regexp: true,
+            ..Default::default()
+        };
+        let patterns = vec!["go".to_string(), "rust".to_string()];
+
+        let regex = compile_patterns(&patterns, &config).unwrap();
+
+        assert!(regex.is_match("rust and go"));
+        assert!(!regex.is_match("rusty gopher"));
+    }
+
+    #[test]
+    fn test_compile_regex_with_regex_flags() {
+        let config = Config {
+            use_regex: true,
+            regex_flags: Some("sx".to_string()),
+            ..Default::default()
+        };
+        let regex = compile_regex("start . end  # any one char", &config)
+            .unwrap()
+            .unwrap();
+
+        assert!(regex.is_match("start\nend"));
+        assert!(!regex.is_match("START\nEND"));
+
+        let config = Config {
+            regex_flags: Some("im".to_string()),
+            ..config
+        };
+        let regex = compile_regex("^end$", &config).unwrap().unwrap();
+
+        assert!(regex.is_match("start\nEND\n"));
+    }
+
+    #[test]
+    fn test_search_slurp_with_dot_matches_new_line_flag() {
+        let config = Config {
+            use_regex: true,
+            regex_flags: Some("s".to_string()),
+            ..Default::default()
+        };
+        let regex = compile_regex("<b>.*</b>", &config).unwrap();
+        let query = crate::search::CompiledQuery::new("", false, regex);
+
+        let results = crate::search::search_slurp(&query, "<b>bold\ntext</b>\n");
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].line, "<b>bold\ntext</b>");
+    }
+
+    #[test]
+    fn test_compile_regex_without_unicode() {
+        let config = Config {
+            use_regex: true,
+            no_unicode: true,
+            ..Default::default()
+        };
+        let ascii = compile_regex(r"\b\w+\b", &config).unwrap().unwrap();
+        let unicode = compile_regex(r"\b\w+\b", &regex_config(true, false))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(ascii.find("été").map(|mat| mat.as_str()), Some("t"));
+        assert_eq!(unicode.find("été").map(|mat| mat.as_str()), Some("été"));
+
+        let digits = compile_regex(r"^\d+$", &config).unwrap().unwrap();
+        assert!(digits.is_match("123"));
+        assert!(!digits.is_match("١٢٣"));
+    }
+
+    #[test]
+    fn test_compile_regex_without_unicode_rejects_invalid_utf8_matches() {
+        let config = Config {
+            use_regex: true,
+            no_unicode: true,
+            ..Default::default()
+        };
+
+        for pattern in [r"\xFF", "a.b", "[^x]"] {
+            assert!(matches!(
+                compile_regex(pattern, &config),
+                Err(ApplicationError::NonUtf8Regex(ref query)) if query == pattern
+            ));
+        }
+
+        let regex = compile_regex("a(?u:.)b", &config).unwrap().unwrap();
+        assert!(regex.is_match("aéb"));
+        assert!(matches!(
+            compile_regex("a[", &config),
+            Err(ApplicationError::InvalidRegex(_))
+        ));
+    }
 }
@@ -0,0 +1,348 @@
+use crate::{config::Config, error::ApplicationError, search::substring_spans};
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    fs::{self, OpenOptions, Permissions},
+    hash::{BuildHasher, RandomState},
+    io::{self, ErrorKind, Write},
+    ops::Range,
+};
+
+pub fn replace_line<'a>(
+    line: &'a str,
+    replacement: &str,
+    config: &Config,
+    regex: &Option<Regex>,
+) -> Cow<'a, str> {
+    if let Some(regex) = regex {
+        return regex.replace_all(line, replacement);
+    }
+
+    let spans = substring_spans(&config.query, line, config.ignore_case);
+
+    if spans.is_empty() {
+        return Cow::Borrowed(line);
+    }
+
+    let mut replaced = String::with_capacity(line.len());
+    let mut last_end = 0;
+
+    for span in spans {
+        replaced.push_str(&line[last_end..span.start]);
+        replaced.push_str(replacement);
+        last_end = span.end;
+    }
+
+    replaced.push_str(&line[last_end..]);
+    Cow::Owned(replaced)
+}
+
+/// Replaces every match in `line` like `replace_line`, also returning the spans of the matches
+/// in `line` and of their replacements in the result, so `--replace-dry-run` can color them.
+pub fn replace_line_with_spans(
+    line: &str,
+    replacement: &str,
+    config: &Config,
+    regex: &Option<Regex>,
+) -> (String, Vec<Range<usize>>, Vec<Range<usize>>) {
+    let replacements: Vec<(Range<usize>, Cow<str>)> = match regex {
+        Some(regex) => regex
+            .captures_iter(line)
+            .map(|captures| {
+                let mut expanded = String::new();
+                captures.expand(replacement, &mut expanded);
+                (
+                    captures.get(0).map_or(0..0, |mat| mat.range()),
+                    expanded.into(),
+                )
+            })
+            .collect(),
+        None => substring_spans(&config.query, line, config.ignore_case)
+            .into_iter()
+            .map(|span| (span, replacement.into()))
+            .collect(),
+    };
+
+    let mut replaced = String::with_capacity(line.len());
+    let mut removed = Vec::with_capacity(replacements.len());
+    let mut added = Vec::with_capacity(replacements.len());
+    let mut last_end = 0;
+
+    for (span, text) in replacements {
+        replaced.push_str(&line[last_end..span.start]);
+        added.push(replaced.len()..replaced.len() + text.len());
+        replaced.push_str(&text);
+        last_end = span.end;
+        removed.push(span);
+    }
+
+    replaced.push_str(&line[last_end..]);
+    (replaced, removed, added)
+}
+
+pub fn replace_content(
+    content: &str,
+    replacement: &str,
+    config: &Config,
+    regex: &Option<Regex>,
+) -> String {
+    let mut replaced = String::with_capacity(content.len());
+
+    for raw_line in content.split_inclusive('\n') {
+        let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        replaced.push_str(&replace_line(line, replacement, config, regex));
+        replaced.push_str(&raw_line[line.len()..]);
+    }
+
+    replaced
+}
+
+pub fn replace_in_place(
+    file_path: &str,
+    replacement: &str,
+    config: &Config,
+    regex: &Option<Regex>,
+) -> Result<bool, ApplicationError> {
+    let content = fs::read_to_string(file_path)
+        .map_err(|error| ApplicationError::from_read_error(file_path, error))?;
+    let replaced = replace_content(&content, replacement, config, regex);
+
+    if replaced == content {
+        return Ok(false);
+    }
+
+    if let Some(suffix) = &config.in_place_backup {
+        fs::copy(file_path, format!("{}{}", file_path, suffix))
+            .map_err(ApplicationError::IOError)?;
+    }
+
+    let permissions = fs::metadata(file_path)
+        .map_err(ApplicationError::IOError)?
+        .permissions();
+    let temp_path =
+        write_temp_file(file_path, &replaced, permissions).map_err(ApplicationError::IOError)?;
+
+    if let Err(error) = fs::rename(&temp_path, file_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(ApplicationError::IOError(error));
+    }
+
+    Ok(true)
+}
+
+/// Writes `contents` to a new file next to `file_path` and returns its path. The name is random
+/// and the file is created with `create_new`, so an existing file or symlink is never written
+/// through, and the file is removed again if writing it fails.
+fn write_temp_file(
+    file_path: &str,
+    contents: &str,
+    permissions: Permissions,
+) -> io::Result<String> {
+    loop {
+        let temp_path = format!(
+            "{}.greplite-{:016x}",
+            file_path,
+            RandomState::new().hash_one(file_path)
+        );
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+            result => result?,
+        };
+
+        let written = file
+            .set_permissions(permissions.clone())
+            .and_then(|()| file.write_all(contents.as_bytes()));
+
+        return match written {
+            Ok(()) => Ok(temp_path),
+            Err(error) => {
+                let _ = fs::remove_file(&temp_path);
+                Err(error)
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    fn substring_config(query: &str, ignore_case: bool) -> Config {
+        Config {
+            query: query.to_string(),
+            ignore_case,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replace_line_substring() {
+        let config = substring_config("foo", false);
+
+        assert_eq!(
+            replace_line("foo and foo", "bar", &config, &None),
+            "bar and bar"
+        );
+        assert!(matches!(
+            replace_line("nothing", "bar", &config, &None),
+            Cow::Borrowed("nothing")
+        ));
+    }
+
+    #[test]
+    fn test_replace_line_with_spans() {
+        let config = substring_config("foo", false);
+
+        assert_eq!(
+            replace_line_with_spans("foo and foo", "barbar", &config, &None),
+            (
+                "barbar and barbar".to_string(),
+                [0..3, 8..11].to_vec(),
+                [0..6, 11..17].to_vec()
+            )
+        );
+
+        let regex = Some(Regex::new(r"(\w+)@(\w+)").unwrap());
+        assert_eq!(
+            replace_line_with_spans("mail a@b now", "$2 at $1", &config, &regex),
+            (
+                "mail b at a now".to_string(),
+                std::iter::once(5..8).collect(),
+                std::iter::once(5..11).collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_replace_line_substring_case_insensitive() {
+        let config = substring_config("foo", true);
+
+        assert_eq!(replace_line("Foo FOO", "bar", &config, &None), "bar bar");
+    }
+
+    #[test]
+    fn test_replace_line_regex_with_groups() {
+        let regex = Some(Regex::new(r"(\w+)@(\w+)").unwrap());
+        let config = substring_config("", false);
+
+        assert_eq!(
+            replace_line("alice@home bob@work", "$2:$1", &config, &regex),
+            "home:alice work:bob"
+        );
+    }
+
+    #[test]
+    fn test_replace_content_keeps_line_endings() {
+        let config = substring_config("foo", false);
+
+        assert_eq!(
+            replace_content("foo\r\nkeep\nfoo", "bar", &config, &None),
+            "bar\r\nkeep\nbar"
+        );
+    }
+
+    #[test]
+    fn test_replace_in_place() {
+        let dir = temp_dir("in-place");
+        let path = dir.join("file.txt");
+        fs::write(&path, "foo one\nkeep\nfoo two\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let changed =
+            replace_in_place(path, "bar", &substring_config("foo", false), &None).unwrap();
+
+        assert!(changed);
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "bar one\nkeep\nbar two\n"
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_in_place_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("in-place-permissions");
+        let path = dir.join("script.sh");
+        fs::write(&path, "echo foo\n").unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o750)).unwrap();
+        let path = path.to_str().unwrap();
+
+        replace_in_place(path, "bar", &substring_config("foo", false), &None).unwrap();
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "echo bar\n");
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_in_place_does_not_follow_old_temp_symlink() {
+        let dir = temp_dir("in-place-symlink");
+        let path = dir.join("file.txt");
+        let target = dir.join("target.txt");
+        fs::write(&path, "foo\n").unwrap();
+        fs::write(&target, "untouched\n").unwrap();
+        std::os::unix::fs::symlink(&target, dir.join("file.txt.greplite-tmp")).unwrap();
+        let path = path.to_str().unwrap();
+
+        replace_in_place(path, "bar", &substring_config("foo", false), &None).unwrap();
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "bar\n");
+        assert_eq!(fs::read_to_string(&target).unwrap(), "untouched\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace_in_place_with_backup() {
+        let dir = temp_dir("in-place-backup");
+        let path = dir.join("file.txt");
+        fs::write(&path, "foo\n").unwrap();
+        let path = path.to_str().unwrap();
+        let config = Config {
+            in_place_backup: Some(".bak".to_string()),
+            ..substring_config("foo", false)
+        };
+
+        replace_in_place(path, "bar", &config, &None).unwrap();
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "bar\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("file.txt.bak")).unwrap(),
+            "foo\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace_in_place_without_match() {
+        let dir = temp_dir("in-place-unchanged");
+        let path = dir.join("file.txt");
+        fs::write(&path, "keep\n").unwrap();
+        let path = path.to_str().unwrap();
+        let config = Config {
+            in_place_backup: Some(".bak".to_string()),
+            ..substring_config("foo", false)
+        };
+
+        assert!(!replace_in_place(path, "bar", &config, &None).unwrap());
+        assert!(!dir.join("file.txt.bak").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
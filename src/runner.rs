@@ -1,31 +1,814 @@
 use crate::{
     config::Config,
     error::ApplicationError,
-    io::{process_directory, process_file, process_input},
-    regex::compile_regex,
+    glob::{expand_glob, is_glob},
+    interrupt,
+    io::{process_directory, process_file, process_input, read_patterns},
+    json::JsonSink,
+    regex::{
+        compile_each_pattern, compile_filename_regex, compile_patterns, compile_regex,
+        SearchPatterns,
+    },
+    stats::{Progress, SearchStats},
 };
-use std::io;
+use regex::Regex;
+use std::{
+    env,
+    io::{self, IsTerminal, Read, Write},
+    ops::ControlFlow,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const STDIN_PATH: &str = "-";
+const STDIN_LABEL: &str = "(standard input)";
+const INPUT_LABEL: &str = "(input)";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+pub fn run(mut config: Config) -> Result<bool, ApplicationError> {
+    config.resolve_color(io::stdout().is_terminal(), |name| env::var(name).ok());
+    interrupt::install();
+
+    if !config.no_glob {
+        config.file_paths = expand_file_paths(&config.file_paths);
+    }
+    run_with_io(&config, &mut io::stdin().lock(), &mut io::stdout().lock())
+}
+
+pub fn run_with_io<R: Read, W: Write>(
+    config: &Config,
+    stdin: &mut R,
+    writer: &mut W,
+) -> Result<bool, ApplicationError> {
+    let mut patterns = match &config.pattern_file {
+        Some(pattern_file) => {
+            let patterns = read_patterns(pattern_file, config.comment_char)?;
+
+            SearchPatterns {
+                regex: Some(compile_patterns(&patterns, config)?),
+                each_pattern: if config.enable_highlighting {
+                    compile_each_pattern(&patterns, config)?
+                } else {
+                    Vec::new()
+                },
+                ..Default::default()
+            }
+        }
+        None => SearchPatterns::from(compile_regex(&config.query, config)?),
+    };
+    patterns.section = compile_filename_regex(&config.section)?;
+    let skip_files = compile_filename_regex(&config.skip_files)?;
+    let mut stats = SearchStats {
+        deadline: config.timeout.map(|timeout| Instant::now() + timeout),
+        interrupted: interrupt::flag(),
+        progress: (config.progress && io::stderr().is_terminal())
+            .then(|| Progress::new(Box::new(io::stderr()), PROGRESS_INTERVAL)),
+        ..Default::default()
+    };
+
+    let searched = if config.json_array {
+        let mut sink = JsonSink::new(&mut *writer);
+        search_sources(config, &patterns, &skip_files, stdin, &mut sink, &mut stats)
+            .and_then(|()| sink.finish().map_err(ApplicationError::IOError))
+    } else {
+        search_sources(config, &patterns, &skip_files, stdin, writer, &mut stats)
+    };
+
+    match searched {
+        // The reader went away (e.g. `greplite ... | head`), so stop quietly rather than
+        // reporting the closed pipe as an error.
+        Err(ApplicationError::IOError(error)) if error.kind() == io::ErrorKind::BrokenPipe => {
+            return Ok(true);
+        }
+        Err(ApplicationError::Interrupted) => {
+            report_interrupted(writer, &mut io::stderr(), &mut stats);
+            return Err(ApplicationError::Interrupted);
+        }
+        result => result?,
+    }
+
+    if let Some(progress) = &mut stats.progress {
+        progress.clear();
+    }
+
+    match write_totals(config, &stats, writer) {
+        Err(ApplicationError::IOError(error)) if error.kind() == io::ErrorKind::BrokenPipe => {
+            return Ok(true);
+        }
+        result => result?,
+    }
+
+    if config.count_files {
+        eprintln!("files searched: {}", stats.files_searched);
+    }
+
+    if config.count_chars {
+        eprintln!(
+            "matched chars: {}, bytes: {}",
+            stats.matched_chars, stats.matched_bytes
+        );
+    }
 
-pub fn run(config: Config) -> Result<(), ApplicationError> {
-    let regex = compile_regex(&config.query, config.use_regex, config.ignore_case)?;
+    if config.match_histogram && !stats.match_histogram.is_empty() {
+        eprintln!("{}", stats.histogram_summary());
+    }
+
+    if let Some(note) = no_match_note(config, &stats) {
+        eprintln!("{}", note);
+    }
 
-    if config.read_from_stdin {
-        process_input("stdin", &mut io::stdin().lock(), &config, &regex)?;
+    if let Some(summary) = top_files_summary(config, &stats) {
+        eprintln!("{}", summary);
+    }
+
+    if config.files_without_match {
+        Ok(stats.files_without_match > 0)
     } else {
+        Ok(stats.files_with_matches > 0)
+    }
+}
+
+/// Writes the output that is only known once every source has been searched.
+fn write_totals<W: Write>(
+    config: &Config,
+    stats: &SearchStats,
+    writer: &mut W,
+) -> Result<(), ApplicationError> {
+    if config.files_with_count_sorted {
+        let separator = config.match_separator.as_deref().unwrap_or(":");
+
+        for (path, count) in stats.top_files(usize::MAX) {
+            writeln!(writer, "{}{}{}", path, separator, count)
+                .map_err(ApplicationError::IOError)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The process exit status for a finished search, as in grep: `0` when something matched (with
+/// `-L`, when a file was listed), `1` when nothing did and the error's own status on failure.
+pub fn exit_status(result: &Result<bool, ApplicationError>) -> i32 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(error) => error.exit_code(),
+    }
+}
+
+/// Expands file arguments containing glob metacharacters that the shell left alone, e.g. when
+/// quoted or on Windows. Paths that exist literally, or whose glob matches nothing, are kept.
+fn expand_file_paths(file_paths: &[String]) -> Vec<String> {
+    file_paths
+        .iter()
+        .flat_map(|file_path| {
+            let matches = if is_glob(file_path) && !Path::new(file_path).exists() {
+                expand_glob(file_path)
+            } else {
+                Vec::new()
+            };
+
+            if matches.is_empty() {
+                vec![file_path.clone()]
+            } else {
+                matches
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+/// The `--top` summary as `path:count` lines, like `--files-with-count`. Returns `None` without
+/// `--top` or when no file matched.
+fn top_files_summary(config: &Config, stats: &SearchStats) -> Option<String> {
+    let separator = config.match_separator.as_deref().unwrap_or(":");
+    let lines: Vec<String> = stats
+        .top_files(config.top?)
+        .into_iter()
+        .map(|(path, count)| format!("{}{}{}", path, separator, count))
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The `--verbose` note for a search that matched nothing, telling an empty search apart from a
+/// pattern that didn't match. Returns `None` without `--verbose` or when something matched.
+fn no_match_note(config: &Config, stats: &SearchStats) -> Option<String> {
+    if !config.verbose || stats.files_with_matches > 0 {
+        return None;
+    }
+
+    Some(match stats.files_searched {
+        0 if config.read_from_stdin || config.inline_input.is_some() => {
+            "no matches found in the input".to_string()
+        }
+        1 => "no matches found in 1 file".to_string(),
+        count => format!("no matches found in {} files", count),
+    })
+}
+
+/// Flushes the results printed before Ctrl-C and notes on `err_writer` how far the search got.
+fn report_interrupted<W: Write, E: Write>(
+    writer: &mut W,
+    err_writer: &mut E,
+    stats: &mut SearchStats,
+) {
+    if let Some(progress) = &mut stats.progress {
+        progress.clear();
+    }
+
+    let _ = writer.flush();
+    let _ = writeln!(
+        err_writer,
+        "interrupted: {} files searched, {} matching lines printed",
+        stats.files_searched, stats.lines_printed
+    );
+}
+
+fn search_sources<R: Read, W: Write>(
+    config: &Config,
+    patterns: &SearchPatterns,
+    skip_files: &Option<Regex>,
+    stdin: &mut R,
+    writer: &mut W,
+    stats: &mut SearchStats,
+) -> Result<(), ApplicationError> {
+    if let Some(input) = &config.inline_input {
+        let _ = process_input(
+            INPUT_LABEL,
+            &mut input.as_bytes(),
+            config,
+            patterns,
+            writer,
+            stats,
+        )?;
+    } else if config.read_from_stdin {
+        let label = config.stdin_label.as_deref().unwrap_or("stdin");
+        let _ = process_input(label, stdin, config, patterns, writer, stats)?;
+    } else {
+        let recursive = config.recursive_search || config.auto_recursive;
+
         for file_path in &config.file_paths {
-            let path = std::path::Path::new(file_path);
+            stats.check_interrupted()?;
+            let path = Path::new(file_path);
 
-            if path.is_dir() && !config.recursive_search {
+            if file_path != STDIN_PATH && path.is_dir() && !recursive {
                 return Err(ApplicationError::DirectoryWithoutRecursive);
             }
 
-            if path.is_dir() && config.recursive_search {
-                process_directory(path, &config, &regex)?;
+            let flow = if file_path == STDIN_PATH {
+                let label = config.stdin_label.as_deref().unwrap_or(STDIN_LABEL);
+                process_input(label, stdin, config, patterns, writer, stats)?
+            } else if path.is_dir() && recursive {
+                process_directory(path, config, patterns, skip_files, writer, stats)?
             } else {
-                process_file(file_path, &config, &regex)?;
+                process_file(file_path, config, patterns, writer, stats)?
+            };
+
+            if let ControlFlow::Break(()) = flow {
+                break;
             }
         }
     }
 
-    Ok(())
+    stats.check_interrupted()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+    use std::{fs, sync::atomic::AtomicBool};
+
+    #[test]
+    fn test_run_with_stdin_among_files() {
+        let dir = temp_dir("stdin-dash");
+        let first = dir.join("first.txt");
+        let last = dir.join("last.txt");
+        fs::write(&first, "rust first\n").unwrap();
+        fs::write(&last, "rust last\n").unwrap();
+
+        let config = Config {
+            query: "rust".to_string(),
+            file_paths: vec![
+                first.display().to_string(),
+                "-".to_string(),
+                last.display().to_string(),
+            ],
+            ..Default::default()
+        };
+        let mut stdin = "rust piped\nc++\n".as_bytes();
+        let mut output = Vec::new();
+
+        let matched = run_with_io(&config, &mut stdin, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(matched);
+        assert_eq!(
+            output,
+            format!(
+                "{}:rust first\n(standard input):rust piped\n{}:rust last\n",
+                first.display(),
+                last.display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_with_print_first_match_without_match() {
+        let config = Config {
+            query: "rust".to_string(),
+            read_from_stdin: true,
+            print_first_match: true,
+            ..Default::default()
+        };
+        let mut stdin = "c++\n".as_bytes();
+        let mut output = Vec::new();
+
+        let matched = run_with_io(&config, &mut stdin, &mut output).unwrap();
+
+        assert!(!matched);
+        assert!(output.is_empty());
+    }
+
+    struct BrokenPipeWriter;
+
+    impl Write for BrokenPipeWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_with_broken_pipe() {
+        let config = Config {
+            query: "rust".to_string(),
+            read_from_stdin: true,
+            ..Default::default()
+        };
+        let mut stdin = "rust
+rust again
+"
+        .as_bytes();
+
+        let matched = run_with_io(&config, &mut stdin, &mut BrokenPipeWriter).unwrap();
+
+        assert!(matched);
+    }
+
+    #[test]
+    fn test_run_with_json_array_broken_pipe() {
+        let config = Config {
+            query: "rust".to_string(),
+            read_from_stdin: true,
+            json_array: true,
+            ..Default::default()
+        };
+
+        let matched =
+            run_with_io(&config, &mut "rust\n".as_bytes(), &mut BrokenPipeWriter).unwrap();
+
+        assert!(matched);
+    }
+
+    #[test]
+    fn test_run_with_inline_input() {
+        let config = Config {
+            query: "rust".to_string(),
+            show_line_numbers: true,
+            inline_input: Some("c++\nrust is fast\ngo\nrust is safe".to_string()),
+            ..Default::default()
+        };
+        let mut stdin = "rust from stdin\n".as_bytes();
+        let mut output = Vec::new();
+
+        let matched = run_with_io(&config, &mut stdin, &mut output).unwrap();
+
+        assert!(matched);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "(input):2: rust is fast\n(input):4: rust is safe\n"
+        );
+    }
+
+    #[test]
+    fn test_run_with_pattern_file_colors_each_pattern() {
+        let dir = temp_dir("pattern-colors");
+        let pattern_file = dir.join("patterns.txt");
+        fs::write(&pattern_file, "error\nwarn\n").unwrap();
+        let config = Config {
+            pattern_file: Some(pattern_file.to_str().unwrap().to_string()),
+            enable_highlighting: true,
+            inline_input: Some("warn then error".to_string()),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        let matched = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+
+        assert!(matched);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "(input):\x1b[1;36mwarn\x1b[0m then \x1b[1;33merror\x1b[0m\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_with_empty_pattern_file_matches_nothing() {
+        let dir = temp_dir("empty-patterns");
+        let pattern_file = dir.join("patterns.txt");
+
+        for content in ["", "# only comments\n"] {
+            fs::write(&pattern_file, content).unwrap();
+            let config = Config {
+                pattern_file: Some(pattern_file.to_str().unwrap().to_string()),
+                comment_char: Some('#'),
+                inline_input: Some("rust\nc++".to_string()),
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+
+            let matched = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+
+            assert!(!matched);
+            assert!(output.is_empty());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_file_paths() {
+        let dir = temp_dir("expand-globs");
+        let literal = dir.join("[notes].txt");
+
+        for path in [
+            dir.join("a.txt"),
+            dir.join("b.txt"),
+            dir.join("c.log"),
+            literal.clone(),
+        ] {
+            fs::write(path, "rust\n").unwrap();
+        }
+
+        let root = dir.display();
+        let file_paths = vec![
+            format!("{}/*.txt", root),
+            literal.display().to_string(),
+            format!("{}/*.md", root),
+            STDIN_PATH.to_string(),
+        ];
+
+        assert_eq!(
+            expand_file_paths(&file_paths),
+            vec![
+                format!("{}/[notes].txt", root),
+                format!("{}/a.txt", root),
+                format!("{}/b.txt", root),
+                format!("{}/[notes].txt", root),
+                format!("{}/*.md", root),
+                STDIN_PATH.to_string(),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_with_empty_file() {
+        let dir = temp_dir("empty-file");
+        let empty = dir.join("empty.txt").display().to_string();
+        fs::write(&empty, "").unwrap();
+
+        for (files_without_match, count, expected_output, expected_matched) in [
+            (true, false, format!("{}\n", empty), true),
+            (false, true, format!("{}:0\n", empty), false),
+            (false, false, String::new(), false),
+        ] {
+            let config = Config {
+                query: "rust".to_string(),
+                file_paths: vec![empty.clone()],
+                files_without_match,
+                count,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+
+            let matched = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+
+            assert_eq!(matched, expected_matched);
+            assert_eq!(String::from_utf8(output).unwrap(), expected_output);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_with_files_with_count_sorted() {
+        let dir = temp_dir("runner-files-with-count");
+        let paths: Vec<String> = [
+            ("a.txt", "rust\n"),
+            ("b.txt", "go\n"),
+            ("c.txt", "rust\nrust\n"),
+        ]
+        .iter()
+        .map(|(name, content)| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path.display().to_string()
+        })
+        .collect();
+        let config = Config {
+            query: "rust".to_string(),
+            file_paths: paths.clone(),
+            files_with_count: true,
+            files_with_count_sorted: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        let matched = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+
+        assert!(matched);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:2\n{}:1\n", paths[2], paths[0])
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_with_files_with_count_sorted_broken_pipe() {
+        let dir = temp_dir("files-with-count-broken-pipe");
+        let path = dir.join("a.txt");
+        fs::write(&path, "rust\n").unwrap();
+        let config = Config {
+            query: "rust".to_string(),
+            file_paths: vec![path.display().to_string()],
+            files_with_count: true,
+            files_with_count_sorted: true,
+            ..Default::default()
+        };
+
+        let matched = run_with_io(&config, &mut "".as_bytes(), &mut BrokenPipeWriter).unwrap();
+
+        assert!(matched);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(exit_status(&Ok(true)), 0);
+        assert_eq!(exit_status(&Ok(false)), 1);
+        assert_eq!(exit_status(&Err(ApplicationError::Timeout)), 1);
+        assert_eq!(exit_status(&Err(ApplicationError::Interrupted)), 130);
+    }
+
+    #[test]
+    fn test_top_files_summary() {
+        let stats = SearchStats {
+            match_counts: vec![
+                ("a.txt".to_string(), 1),
+                ("b.txt".to_string(), 3),
+                ("c.txt".to_string(), 2),
+            ],
+            ..Default::default()
+        };
+        let config = Config {
+            top: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            top_files_summary(&config, &stats).as_deref(),
+            Some("b.txt:3\nc.txt:2")
+        );
+        assert_eq!(top_files_summary(&config, &SearchStats::default()), None);
+        assert_eq!(top_files_summary(&Config::default(), &stats), None);
+    }
+
+    #[test]
+    fn test_no_match_note() {
+        let stats = |files_searched, files_with_matches| SearchStats {
+            files_searched,
+            files_with_matches,
+            ..Default::default()
+        };
+        let config = Config {
+            query: "rust".to_string(),
+            file_paths: vec!["a.txt".to_string()],
+            verbose: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            no_match_note(&config, &stats(3, 0)).as_deref(),
+            Some("no matches found in 3 files")
+        );
+        assert_eq!(
+            no_match_note(&config, &stats(1, 0)).as_deref(),
+            Some("no matches found in 1 file")
+        );
+        assert_eq!(no_match_note(&config, &stats(3, 1)), None);
+
+        let quiet = Config {
+            verbose: false,
+            ..config
+        };
+        assert_eq!(no_match_note(&quiet, &stats(3, 0)), None);
+
+        let stdin = Config {
+            verbose: true,
+            read_from_stdin: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            no_match_note(&stdin, &stats(0, 0)).as_deref(),
+            Some("no matches found in the input")
+        );
+    }
+
+    #[test]
+    fn test_search_sources_stops_when_interrupted() {
+        static INTERRUPTED: AtomicBool = AtomicBool::new(true);
+
+        let dir = temp_dir("interrupted");
+        let path = dir.join("a.txt").display().to_string();
+        fs::write(&path, "rust\n").unwrap();
+        let config = Config {
+            query: "rust".to_string(),
+            file_paths: vec![path],
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut stats = SearchStats {
+            interrupted: Some(&INTERRUPTED),
+            ..Default::default()
+        };
+
+        let result = search_sources(
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut "".as_bytes(),
+            &mut output,
+            &mut stats,
+        );
+
+        assert!(matches!(result, Err(ApplicationError::Interrupted)));
+        assert_eq!(stats.files_searched, 0);
+
+        let config = Config {
+            query: "rust".to_string(),
+            inline_input: Some("rust\n".to_string()),
+            ..Default::default()
+        };
+        let result = search_sources(
+            &config,
+            &SearchPatterns::default(),
+            &None,
+            &mut "".as_bytes(),
+            &mut output,
+            &mut stats,
+        );
+
+        assert!(matches!(result, Err(ApplicationError::Interrupted)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_interrupted_flushes_results() {
+        let mut writer = io::BufWriter::new(Vec::new());
+        let mut err_output = Vec::new();
+        let mut stats = SearchStats {
+            files_searched: 3,
+            lines_printed: 2,
+            ..Default::default()
+        };
+        writeln!(writer, "a.txt:rust").unwrap();
+
+        report_interrupted(&mut writer, &mut err_output, &mut stats);
+
+        assert_eq!(writer.get_ref(), b"a.txt:rust\n");
+        assert_eq!(
+            String::from_utf8(err_output).unwrap(),
+            "interrupted: 3 files searched, 2 matching lines printed\n"
+        );
+    }
+
+    #[test]
+    fn test_run_with_json_array() {
+        for (input, expected_records, expected_matched) in
+            [("rust 1\nc++\nrust 2\n", 2, true), ("c++\ngo\n", 0, false)]
+        {
+            let config = Config {
+                query: "rust".to_string(),
+                inline_input: Some(input.to_string()),
+                json_array: true,
+                ..Default::default()
+            };
+            let mut output = Vec::new();
+
+            let matched = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+            let output = String::from_utf8(output).unwrap();
+            let array = output.trim_end();
+
+            assert_eq!(matched, expected_matched);
+            assert!(array.starts_with('[') && array.ends_with(']'));
+            assert_eq!(array.matches("\"line_number\"").count(), expected_records);
+        }
+
+        let config = Config {
+            query: "rust".to_string(),
+            inline_input: Some("rust 1\nc++\nrust 2\n".to_string()),
+            json_array: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        let _ = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[\n\
+             {\"path\":\"(input)\",\"line_number\":1,\"byte_offset\":0,\"line\":\"rust 1\",\
+             \"submatches\":[{\"start\":0,\"end\":4}]},\n\
+             {\"path\":\"(input)\",\"line_number\":3,\"byte_offset\":11,\"line\":\"rust 2\",\
+             \"submatches\":[{\"start\":0,\"end\":4}]}\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn test_run_with_stdin_only() {
+        let config = Config {
+            query: "rust".to_string(),
+            read_from_stdin: true,
+            ..Default::default()
+        };
+        let mut stdin = "c++\nrust\n".as_bytes();
+        let mut output = Vec::new();
+
+        let matched = run_with_io(&config, &mut stdin, &mut output).unwrap();
+
+        assert!(matched);
+        assert_eq!(String::from_utf8(output).unwrap(), "stdin:rust\n");
+    }
+
+    #[test]
+    fn test_run_with_stdin_label() {
+        let config = Config {
+            query: "rust".to_string(),
+            read_from_stdin: true,
+            stdin_label: Some("myservice".to_string()),
+            ..Default::default()
+        };
+        let mut stdin = "rust\n".as_bytes();
+        let mut output = Vec::new();
+
+        run_with_io(&config, &mut stdin, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "myservice:rust\n");
+    }
+
+    #[test]
+    fn test_run_with_auto_recursive_directory() {
+        let dir = temp_dir("auto-recursive");
+        fs::create_dir(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested").join("lib.rs"), "rust\n").unwrap();
+
+        let mut config = Config {
+            query: "rust".to_string(),
+            file_paths: vec![dir.display().to_string()],
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+
+        let result = run_with_io(&config, &mut "".as_bytes(), &mut output);
+        assert!(matches!(
+            result,
+            Err(ApplicationError::DirectoryWithoutRecursive)
+        ));
+
+        config.auto_recursive = true;
+        let matched = run_with_io(&config, &mut "".as_bytes(), &mut output).unwrap();
+
+        assert!(matched);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:rust\n", dir.join("nested").join("lib.rs").display())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
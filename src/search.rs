@@ -1,14 +1,155 @@
+use crate::error::ApplicationError;
 use regex::Regex;
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
 
-fn compare_lines(query: &str, line: &str, ignore_case: bool, regex: &Option<Regex>) -> bool {
-    if let Some(regex) = regex {
-        regex.is_match(line)
-    } else {
-        if ignore_case {
-            line.to_lowercase().contains(&query.to_lowercase())
-        } else {
-            line.contains(query)
+const DEADLINE_CHECK_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMatch<'a> {
+    pub line_number: usize,
+    pub line: &'a str,
+    pub byte_offset: usize,
+    pub spans: Vec<Range<usize>>,
+    pub is_context: bool,
+}
+
+/// A search pattern prepared once per search, so matching a line does no per-query work such
+/// as lowercasing the pattern.
+#[derive(Debug, Clone)]
+pub struct CompiledQuery {
+    query: String,
+    lowered: String,
+    ignore_case: bool,
+    regex: Option<Regex>,
+    fuzzy: Option<(Vec<char>, usize)>,
+    field: Option<(usize, char)>,
+}
+
+impl CompiledQuery {
+    pub fn new(query: &str, ignore_case: bool, regex: Option<Regex>) -> Self {
+        CompiledQuery {
+            query: query.to_string(),
+            lowered: if ignore_case {
+                query.to_lowercase()
+            } else {
+                String::new()
+            },
+            ignore_case,
+            regex,
+            fuzzy: None,
+            field: None,
+        }
+    }
+
+    /// Matches lines containing the query with up to `max_edits` inserted, deleted or
+    /// substituted characters, for `--fuzzy`. Fuzzy matches have no spans.
+    pub fn with_fuzzy(mut self, max_edits: Option<usize>) -> Self {
+        self.fuzzy = max_edits.map(|max_edits| {
+            let query = if self.ignore_case {
+                &self.lowered
+            } else {
+                &self.query
+            };
+            (query.chars().collect(), max_edits)
+        });
+        self
+    }
+
+    /// Only matches the `field`th (1-based) `delimiter`-separated field of each line, for
+    /// `--field`. Lines with fewer fields never match.
+    pub fn with_field(mut self, field: Option<usize>, delimiter: char) -> Self {
+        self.field = field.map(|field| (field, delimiter));
+        self
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.field_range(line)
+            .is_some_and(|range| self.is_text_match(&line[range]))
+    }
+
+    pub fn spans(&self, line: &str) -> Vec<Range<usize>> {
+        match self.field_range(line) {
+            Some(range) => self
+                .text_spans(&line[range.clone()])
+                .into_iter()
+                .map(|span| span.start + range.start..span.end + range.start)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn field_range(&self, line: &str) -> Option<Range<usize>> {
+        let Some((field, delimiter)) = self.field else {
+            return Some(0..line.len());
+        };
+        let mut start = 0;
+
+        for (index, part) in line.split(delimiter).enumerate() {
+            if index + 1 == field {
+                return Some(start..start + part.len());
+            }
+            start += part.len() + delimiter.len_utf8();
+        }
+
+        None
+    }
+
+    fn is_text_match(&self, line: &str) -> bool {
+        if let Some((query, max_edits)) = &self.fuzzy {
+            return if self.ignore_case {
+                fuzzy_contains(query, &line.to_lowercase(), *max_edits)
+            } else {
+                fuzzy_contains(query, line, *max_edits)
+            };
+        }
+
+        match &self.regex {
+            Some(regex) => regex.is_match(line),
+            None if !self.ignore_case => line.contains(&self.query),
+            None if self.query.is_ascii() && line.is_ascii() => {
+                contains_ignore_ascii_case(&self.query, line)
+            }
+            None => line.to_lowercase().contains(&self.lowered),
+        }
+    }
+
+    fn text_spans(&self, line: &str) -> Vec<Range<usize>> {
+        if self.fuzzy.is_some() {
+            return Vec::new();
+        }
+
+        match &self.regex {
+            Some(regex) => regex.find_iter(line).map(|mat| mat.range()).collect(),
+            None => self.substring_spans(line),
+        }
+    }
+
+    fn substring_spans(&self, line: &str) -> Vec<Range<usize>> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+
+        if !self.ignore_case {
+            return line
+                .match_indices(&self.query)
+                .map(|(pos, _)| pos..pos + self.query.len())
+                .collect();
+        }
+
+        if self.query.is_ascii() && line.is_ascii() {
+            return ascii_spans(&self.query, line);
         }
+
+        line.to_lowercase()
+            .match_indices(&self.lowered)
+            .map(|(pos, _)| pos..pos + self.lowered.len())
+            .filter(|span| span.end <= line.len())
+            .filter(|span| line.is_char_boundary(span.start) && line.is_char_boundary(span.end))
+            .collect()
     }
 }
 
@@ -17,20 +158,424 @@ pub fn search<'a>(
     content: &'a str,
     ignore_case: bool,
     regex: &Option<Regex>,
-) -> Vec<(usize, &'a str)> {
-    content
-        .lines()
-        .enumerate()
-        .filter(|(_, line)| compare_lines(query, line, ignore_case, regex))
-        .map(|(line_num, line)| (line_num + 1, line))
+    range: Option<(usize, usize)>,
+) -> Vec<LineMatch<'a>> {
+    let query = CompiledQuery::new(query, ignore_case, regex.clone());
+    search_until(&query, content, range, None, None, None).unwrap_or_default()
+}
+
+pub fn search_until<'a>(
+    query: &CompiledQuery,
+    content: &'a str,
+    range: Option<(usize, usize)>,
+    max_count: Option<usize>,
+    deadline: Option<Instant>,
+    interrupted: Option<&AtomicBool>,
+) -> Result<Vec<LineMatch<'a>>, ApplicationError> {
+    let (start, end) = range.unwrap_or((1, usize::MAX));
+    let mut results = Vec::new();
+    let mut byte_offset = 0;
+
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line_num = index + 1;
+        let line_offset = byte_offset;
+        byte_offset += raw_line.len();
+
+        if line_num < start {
+            continue;
+        }
+
+        if line_num > end {
+            break;
+        }
+
+        if index % DEADLINE_CHECK_INTERVAL == 0 {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(ApplicationError::Timeout);
+            }
+
+            if interrupted.is_some_and(|flag| flag.load(Ordering::SeqCst)) {
+                return Err(ApplicationError::Interrupted);
+            }
+        }
+
+        let line = trim_line_ending(raw_line);
+
+        if query.is_match(line) {
+            results.push(LineMatch {
+                line_number: line_num,
+                line,
+                byte_offset: line_offset,
+                spans: query.spans(line),
+                is_context: false,
+            });
+
+            if max_count.is_some_and(|max_count| results.len() >= max_count) {
+                break;
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Returns the lines of `content` within `range` that are not in `results`, for `-v`.
+pub fn invert_matches<'a>(
+    content: &'a str,
+    results: &[LineMatch],
+    range: Option<(usize, usize)>,
+) -> Vec<LineMatch<'a>> {
+    let (start, end) = range.unwrap_or((1, usize::MAX));
+    let mut matched = results.iter().map(|result| result.line_number).peekable();
+    let mut inverted = Vec::new();
+    let mut byte_offset = 0;
+
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        let line_offset = byte_offset;
+        byte_offset += raw_line.len();
+
+        if line_number > end {
+            break;
+        }
+
+        if line_number < start || matched.next_if_eq(&line_number).is_some() {
+            continue;
+        }
+
+        inverted.push(LineMatch {
+            line_number,
+            line: trim_line_ending(raw_line),
+            byte_offset: line_offset,
+            spans: Vec::new(),
+            is_context: false,
+        });
+    }
+
+    inverted
+}
+
+pub fn search_with_spans<'a>(
+    query: &str,
+    content: &'a str,
+    ignore_case: bool,
+    regex: &Option<Regex>,
+) -> Vec<(usize, &'a str, Vec<Range<usize>>)> {
+    search(query, content, ignore_case, regex, None)
+        .into_iter()
+        .map(|mat| (mat.line_number, mat.line, mat.spans))
+        .collect()
+}
+
+pub fn substring_spans(query: &str, line: &str, ignore_case: bool) -> Vec<Range<usize>> {
+    CompiledQuery::new(query, ignore_case, None).spans(line)
+}
+
+/// Whether some substring of `line` is within `max_edits` edits of `query`, using Sellers'
+/// variant of the Levenshtein distance where a match may start anywhere in the line.
+fn fuzzy_contains(query: &[char], line: &str, max_edits: usize) -> bool {
+    if query.len() <= max_edits {
+        return true;
+    }
+
+    let mut column: Vec<usize> = (0..=query.len()).collect();
+
+    for c in line.chars() {
+        let mut diagonal = 0;
+
+        for i in 1..=query.len() {
+            let above = column[i];
+            column[i] = (diagonal + usize::from(query[i - 1] != c))
+                .min(above + 1)
+                .min(column[i - 1] + 1);
+            diagonal = above;
+        }
+
+        if column[query.len()] <= max_edits {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn contains_ignore_ascii_case(query: &str, line: &str) -> bool {
+    find_ignore_ascii_case(query.as_bytes(), line.as_bytes(), 0).is_some()
+}
+
+fn find_ignore_ascii_case(query: &[u8], line: &[u8], from: usize) -> Option<usize> {
+    let Some(&first) = query.first() else {
+        return Some(from);
+    };
+    let (lower, upper) = (first.to_ascii_lowercase(), first.to_ascii_uppercase());
+    let last_start = line.len().checked_sub(query.len())?;
+
+    (from..=last_start).find(|&pos| {
+        (line[pos] == lower || line[pos] == upper)
+            && line[pos + 1..pos + query.len()].eq_ignore_ascii_case(&query[1..])
+    })
+}
+
+fn ascii_spans(query: &str, line: &str) -> Vec<Range<usize>> {
+    let (query, line) = (query.as_bytes(), line.as_bytes());
+    let mut spans = Vec::new();
+    let mut pos = 0;
+
+    if query.is_empty() {
+        return spans;
+    }
+
+    while let Some(start) = find_ignore_ascii_case(query, line, pos) {
+        spans.push(start..start + query.len());
+        pos = start + query.len();
+    }
+
+    spans
+}
+
+fn trim_line_ending(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+pub fn search_multiline<'a>(
+    content: &'a str,
+    regex: &Regex,
+    range: Option<(usize, usize)>,
+) -> Vec<LineMatch<'a>> {
+    let (start, end) = range.unwrap_or((1, usize::MAX));
+    let mut results: Vec<LineMatch<'a>> = Vec::new();
+    let mut last_block_end = 0;
+    let mut in_range = false;
+    let mut line_num = 1;
+    let mut counted_to = 0;
+
+    for mat in regex.find_iter(content) {
+        if mat.is_empty() {
+            continue;
+        }
+
+        if mat.start() < last_block_end {
+            last_block_end = line_end(content, mat.end()).max(last_block_end);
+
+            if let Some(block) = results.last_mut().filter(|_| in_range) {
+                block.line = &content[block.byte_offset..last_block_end];
+                block
+                    .spans
+                    .push(mat.start() - block.byte_offset..mat.end() - block.byte_offset);
+            }
+            continue;
+        }
+
+        line_num += content[counted_to..mat.start()].matches('\n').count();
+        counted_to = mat.start();
+
+        let block_start = content[..mat.start()].rfind('\n').map_or(0, |pos| pos + 1);
+        let block_end = line_end(content, mat.end());
+        last_block_end = block_end;
+
+        in_range = line_num >= start && line_num <= end;
+
+        if in_range {
+            results.push(LineMatch {
+                line_number: line_num,
+                line: &content[block_start..block_end],
+                byte_offset: block_start,
+                spans: std::iter::once(mat.start() - block_start..mat.end() - block_start)
+                    .collect(),
+                is_context: false,
+            });
+        }
+    }
+
+    results
+}
+
+pub fn search_slurp<'a>(query: &CompiledQuery, content: &'a str) -> Vec<LineMatch<'a>> {
+    let mut line_num = 1;
+    let mut counted_to = 0;
+
+    query
+        .spans(content)
+        .into_iter()
+        .filter(|span| !span.is_empty())
+        .map(|span| {
+            line_num += content[counted_to..span.start].matches('\n').count();
+            counted_to = span.start;
+
+            LineMatch {
+                line_number: line_num,
+                line: &content[span.clone()],
+                byte_offset: span.start,
+                spans: std::iter::once(0..span.len()).collect(),
+                is_context: false,
+            }
+        })
         .collect()
 }
 
+pub fn with_context<'a>(
+    content: &'a str,
+    results: Vec<LineMatch<'a>>,
+    before: usize,
+    after: usize,
+) -> Vec<LineMatch<'a>> {
+    let mut lines = Vec::new();
+    let mut byte_offset = 0;
+
+    for raw_line in content.split_inclusive('\n') {
+        lines.push((byte_offset, trim_line_ending(raw_line)));
+        byte_offset += raw_line.len();
+    }
+
+    let context_line = |line_number: usize| {
+        let (byte_offset, line) = lines[line_number - 1];
+        LineMatch {
+            line_number,
+            line,
+            byte_offset,
+            spans: Vec::new(),
+            is_context: true,
+        }
+    };
+
+    let mut output = Vec::with_capacity(results.len());
+    let mut next_line = 1;
+    let mut after_until = 0;
+
+    for result in results {
+        let first = result.line_number;
+        let last = first + result.line.matches('\n').count();
+
+        while next_line <= after_until.min(first - 1) {
+            output.push(context_line(next_line));
+            next_line += 1;
+        }
+
+        next_line = next_line.max(first.saturating_sub(before));
+
+        while next_line < first {
+            output.push(context_line(next_line));
+            next_line += 1;
+        }
+
+        output.push(result);
+        next_line = next_line.max(last + 1);
+        after_until = last + after;
+    }
+
+    while next_line <= after_until.min(lines.len()) {
+        output.push(context_line(next_line));
+        next_line += 1;
+    }
+
+    output
+}
+
+/// Like [`with_context`], but only the first result gets context lines; later results are
+/// returned as they are, replacing any context line they overlap.
+pub fn with_first_context<'a>(
+    content: &'a str,
+    results: Vec<LineMatch<'a>>,
+    before: usize,
+    after: usize,
+) -> Vec<LineMatch<'a>> {
+    let mut results = results.into_iter();
+    let Some(first) = results.next() else {
+        return Vec::new();
+    };
+
+    let mut output = with_context(content, vec![first], before, after);
+    let window_len = output.len();
+
+    for result in results {
+        match output[..window_len]
+            .iter()
+            .position(|line| line.line_number == result.line_number)
+        {
+            Some(index) => output[index] = result,
+            None => output.push(result),
+        }
+    }
+
+    output
+}
+
+/// Returns the line numbers of the sections of `content` named `name`, for `--section`. A
+/// section starts after each line matching `delimiter` and runs until the next one; it is named
+/// by the delimiter's first capture group, or by the whole match if the regex has no groups.
+pub fn section_lines(content: &str, delimiter: &Regex, name: &str) -> Vec<Range<usize>> {
+    let mut sections = Vec::new();
+    let mut current: Option<usize> = None;
+    let mut line_count = 0;
+
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        line_count = line_number;
+
+        let Some(captures) = delimiter.captures(trim_line_ending(raw_line)) else {
+            continue;
+        };
+
+        if let Some(start) = current.take() {
+            sections.push(start..line_number);
+        }
+
+        let section_name = captures.get(1).or_else(|| captures.get(0));
+
+        if section_name.is_some_and(|section_name| section_name.as_str() == name) {
+            current = Some(line_number + 1);
+        }
+    }
+
+    if let Some(start) = current {
+        sections.push(start..line_count + 1);
+    }
+
+    sections
+}
+
+fn line_end(content: &str, from: usize) -> usize {
+    content[from..]
+        .find('\n')
+        .map_or(content.len(), |pos| from + pos)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::error::ApplicationError;
-    use crate::regex::compile_regex;
+    use crate::{config::Config, regex::compile_regex};
+
+    #[test]
+    fn test_section_lines() {
+        let content = "top = 1\n[server]\nport = 80\n[client]\nport = 81\n[server]\nhost = a\n";
+        let delimiter = Regex::new(r"^\[(.+)\]$").unwrap();
+
+        assert_eq!(
+            section_lines(content, &delimiter, "server"),
+            vec![3..4, 7..8]
+        );
+        assert_eq!(section_lines(content, &delimiter, "client"), vec![5..6]);
+        assert!(section_lines(content, &delimiter, "missing").is_empty());
+
+        let delimiter = Regex::new(r"^\[.+\]$").unwrap();
+        assert_eq!(section_lines(content, &delimiter, "[client]"), vec![5..6]);
+    }
+
+    fn positions<'a>(matches: Vec<LineMatch<'a>>) -> Vec<(usize, usize, &'a str)> {
+        matches
+            .into_iter()
+            .map(|mat| (mat.line_number, mat.byte_offset, mat.line))
+            .collect()
+    }
+
+    fn regex_config(use_regex: bool, ignore_case: bool) -> Config {
+        Config {
+            use_regex,
+            ignore_case,
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn test_compile_regex_no_regex() {
@@ -38,7 +583,7 @@ mod tests {
         let use_regex = false;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(None) => (),
@@ -52,7 +597,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(Some(regex)) => {
@@ -69,7 +614,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Err(ApplicationError::InvalidRegex(ref s)) => {
@@ -88,7 +633,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = true;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(Some(regex)) => {
@@ -107,7 +652,7 @@ mod tests {
         let use_regex = true;
         let ignore_case = false;
 
-        let result = compile_regex(query, use_regex, ignore_case);
+        let result = compile_regex(query, &regex_config(use_regex, ignore_case));
 
         match result {
             Ok(Some(regex)) => {
@@ -119,34 +664,34 @@ mod tests {
     }
 
     #[test]
-    fn test_compare_lines_case_sensitive() {
+    fn test_compiled_query_is_match_case_sensitive() {
         let query = "duct";
         let line = "duct tape";
-        assert!(compare_lines(query, line, false, &None));
+        assert!(CompiledQuery::new(query, false, None).is_match(line));
         let line2 = "Duct tape";
-        assert!(!compare_lines(query, line2, false, &None));
+        assert!(!CompiledQuery::new(query, false, None).is_match(line2));
     }
 
     #[test]
-    fn test_compare_lines_case_insensitive() {
+    fn test_compiled_query_is_match_case_insensitive() {
         let query = "rUsT";
         let line = "Rust is great";
-        assert!(compare_lines(query, line, true, &None));
+        assert!(CompiledQuery::new(query, true, None).is_match(line));
         let line2 = "rust is great";
-        assert!(compare_lines(query, line2, true, &None));
+        assert!(CompiledQuery::new(query, true, None).is_match(line2));
     }
 
     #[test]
-    fn test_compare_lines_with_regex() {
+    fn test_compiled_query_is_match_with_regex() {
         let query = "^Rust";
         let use_regex = true;
         let ignore_case = false;
 
-        let regex = compile_regex(query, use_regex, ignore_case)
+        let regex = compile_regex(query, &regex_config(use_regex, ignore_case))
             .unwrap()
             .unwrap();
         let line = "Rust is great";
-        assert!(compare_lines("Rust", line, false, &Some(regex)));
+        assert!(CompiledQuery::new("Rust", false, Some(regex)).is_match(line));
     }
 
     #[test]
@@ -159,8 +704,8 @@ Pick three.
 Duct tape.";
 
         assert_eq!(
-            vec![(2, "safe, fast, productive.")],
-            search(query, content, false, &None)
+            vec![(2, 6, "safe, fast, productive.")],
+            positions(search(query, content, false, &None, None))
         );
     }
 
@@ -174,8 +719,8 @@ Pick three.
 Trust me.";
 
         assert_eq!(
-            vec![(1, "Rust:"), (4, "Trust me.")],
-            search(query, content, true, &None)
+            vec![(1, 0, "Rust:"), (4, 42, "Trust me.")],
+            positions(search(query, content, true, &None, None))
         );
     }
 
@@ -188,10 +733,12 @@ safe, fast, productive.
 Pick three.
 Rusty nails.";
 
-        let regex = compile_regex(query, true, false).unwrap().unwrap();
+        let regex = compile_regex(query, &regex_config(true, false))
+            .unwrap()
+            .unwrap();
         assert_eq!(
-            vec![(1, "Rust:"), (4, "Rusty nails.")],
-            search(query, content, false, &Some(regex))
+            vec![(1, 0, "Rust:"), (4, 42, "Rusty nails.")],
+            positions(search(query, content, false, &Some(regex), None))
         );
     }
 
@@ -204,10 +751,414 @@ safe, fast, productive.
 Pick three.
 Rusty nails.";
 
-        let regex = compile_regex(query, true, true).unwrap().unwrap();
+        let regex = compile_regex(query, &regex_config(true, true))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            vec![(1, 0, "Rust:"), (4, 42, "Rusty nails.")],
+            positions(search(query, content, false, &Some(regex), None))
+        );
+    }
+
+    #[test]
+    fn test_search_within_range() {
+        let query = "rust";
+        let content = "\
+rust one
+rust two
+rust three
+rust four
+rust five";
+
+        assert_eq!(
+            vec![
+                (2, 9, "rust two"),
+                (3, 18, "rust three"),
+                (4, 29, "rust four")
+            ],
+            positions(search(query, content, false, &None, Some((2, 4))))
+        );
+    }
+
+    #[test]
+    fn test_search_range_past_end() {
+        let query = "rust";
+        let content = "rust one\nrust two";
+
+        assert_eq!(
+            vec![(2, 9, "rust two")],
+            positions(search(query, content, false, &None, Some((2, 100))))
+        );
+        assert!(search(query, content, false, &None, Some((3, 5))).is_empty());
+    }
+
+    fn multiline_config() -> Config {
+        Config {
+            use_regex: true,
+            multiline: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search_multiline_spanning_match() {
+        let content = "\
+fn main() {
+    let x = 1;
+}
+fn other() {
+}";
+
+        let regex = compile_regex(r"fn other\(\) \{\n\}", &multiline_config())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            vec![(4, 29, "fn other() {\n}")],
+            positions(search_multiline(content, &regex, None))
+        );
+    }
+
+    #[test]
+    fn test_search_multiline_reports_start_lines() {
+        let content = "a\nbegin\nmiddle\nend\nb\nbegin\nend";
+
+        let regex = compile_regex("begin.*?end", &multiline_config())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            vec![(2, 2, "begin\nmiddle\nend"), (6, 21, "begin\nend")],
+            positions(search_multiline(content, &regex, None))
+        );
+        assert_eq!(
+            vec![(6, 21, "begin\nend")],
+            positions(search_multiline(content, &regex, Some((3, 10))))
+        );
+    }
+
+    #[test]
+    fn test_search_byte_offsets_multibyte() {
+        let content = "héllo rust\nwörld\r\nrust ünïcode\n";
+
+        assert_eq!(
+            vec![(1, 0, "héllo rust"), (3, 20, "rust ünïcode")],
+            positions(search("rust", content, false, &None, None))
+        );
+    }
+
+    #[test]
+    fn test_search_until_interrupted() {
+        let content = "rust is fast\n".repeat(1_000);
+        let query = CompiledQuery::new("rust", false, None);
+        let interrupted = AtomicBool::new(true);
+
+        let result = search_until(&query, &content, None, None, None, Some(&interrupted));
+
+        assert!(matches!(result, Err(ApplicationError::Interrupted)));
+    }
+
+    #[test]
+    fn test_search_until_times_out() {
+        let content = "rust is fast\n".repeat(100_000);
+
+        let query = CompiledQuery::new("rust", false, None);
+
+        let result = search_until(&query, &content, None, None, Some(Instant::now()), None);
+
+        assert!(
+            matches!(result, Err(ApplicationError::Timeout)),
+            "Expected Err(ApplicationError::Timeout), got {:?}",
+            result.map(|results| results.len())
+        );
+    }
+
+    #[test]
+    fn test_search_until_without_deadline() {
+        let content = "rust\nc++\nrust";
+        let query = CompiledQuery::new("rust", false, None);
+
+        assert_eq!(
+            vec![(1, 0, "rust"), (3, 9, "rust")],
+            positions(search_until(&query, content, None, None, None, None).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_search_until_stops_at_max_count() {
+        let content = "rust one\nc++\nrust two\nrust three";
+        let query = CompiledQuery::new("rust", false, None);
+
+        assert_eq!(
+            vec![(1, 0, "rust one")],
+            positions(search_until(&query, content, None, Some(1), None, None).unwrap())
+        );
+        assert_eq!(
+            vec![(1, 0, "rust one"), (3, 13, "rust two")],
+            positions(search_until(&query, content, None, Some(2), None, None).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_search_with_spans_substring() {
+        let content = "rust and more rust\nc++\nRust RUST";
+
+        assert_eq!(
+            vec![
+                (1, "rust and more rust", vec![0..4, 14..18]),
+                (3, "Rust RUST", vec![0..4, 5..9])
+            ],
+            search_with_spans("rust", content, true, &None)
+        );
+    }
+
+    #[test]
+    fn test_search_with_spans_regex() {
+        let content = "a1 b22 c333\nnone";
+        let regex = compile_regex(r"\d+", &regex_config(true, false)).unwrap();
+
+        assert_eq!(
+            vec![(1, "a1 b22 c333", vec![1..2, 4..6, 8..11])],
+            search_with_spans(r"\d+", content, false, &regex)
+        );
+    }
+
+    #[test]
+    fn test_compiled_query_spans_case_sensitive() {
+        assert_eq!(
+            CompiledQuery::new("ab", false, None).spans("ab AB ab"),
+            vec![0..2, 6..8]
+        );
+        assert!(CompiledQuery::new("", false, None).spans("ab").is_empty());
+    }
+
+    #[test]
+    fn test_search_line_match_fields() {
+        let content = "c++\nrust and Rust\n";
+
+        assert_eq!(
+            vec![LineMatch {
+                line_number: 2,
+                line: "rust and Rust",
+                byte_offset: 4,
+                spans: vec![0..4, 9..13],
+                is_context: false,
+            }],
+            search("rust", content, true, &None, None)
+        );
+    }
+
+    #[test]
+    fn test_search_multiline_line_match_spans() {
+        let content = "x\nbegin a end begin b\nend\n";
+        let regex = compile_regex("begin.*?end", &multiline_config())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            vec![LineMatch {
+                line_number: 2,
+                line: "begin a end begin b\nend",
+                byte_offset: 2,
+                spans: vec![0..11, 12..23],
+                is_context: false,
+            }],
+            search_multiline(content, &regex, None)
+        );
+    }
+
+    #[test]
+    fn test_search_slurp_regex_spans_lines() {
+        let content = "{\n  \"id\": 1,\n  \"name\": \"rust\"\n}\n";
+        let config = Config {
+            use_regex: true,
+            slurp: true,
+            ..Default::default()
+        };
+        let regex = compile_regex(r#"\{.*"rust".*\}"#, &config).unwrap();
+
+        assert_eq!(
+            vec![LineMatch {
+                line_number: 1,
+                line: "{\n  \"id\": 1,\n  \"name\": \"rust\"\n}",
+                byte_offset: 0,
+                spans: std::iter::once(0..31).collect(),
+                is_context: false,
+            }],
+            search_slurp(&CompiledQuery::new("", false, regex), content)
+        );
+    }
+
+    #[test]
+    fn test_search_slurp_substring_offsets() {
+        let content = "a rust\nb\nc Rust";
+
+        assert_eq!(
+            positions(search_slurp(
+                &CompiledQuery::new("rust", true, None),
+                content
+            )),
+            vec![(1, 2, "rust"), (3, 11, "Rust")]
+        );
+    }
+
+    fn context_summary<'a>(matches: Vec<LineMatch<'a>>) -> Vec<(usize, &'a str, bool)> {
+        matches
+            .into_iter()
+            .map(|mat| (mat.line_number, mat.line, mat.is_context))
+            .collect()
+    }
+
+    #[test]
+    fn test_with_context_merges_overlapping_windows() {
+        let content = "a\nrust 1\nb\nc\nrust 2\nd\ne\nf\n";
+        let results = search("rust", content, false, &None, None);
+
+        assert_eq!(
+            vec![
+                (1, "a", true),
+                (2, "rust 1", false),
+                (3, "b", true),
+                (4, "c", true),
+                (5, "rust 2", false),
+                (6, "d", true),
+            ],
+            context_summary(with_context(content, results, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_with_first_context() {
+        let content = "a\nrust 1\nrust 2\nb\nc\nrust 3\nd\n";
+        let results = search("rust", content, false, &None, None);
+
+        assert_eq!(
+            vec![
+                (1, "a", true),
+                (2, "rust 1", false),
+                (3, "rust 2", false),
+                (6, "rust 3", false),
+            ],
+            context_summary(with_first_context(content, results, 1, 1))
+        );
+        assert!(with_first_context(content, Vec::new(), 1, 1).is_empty());
+    }
+
+    #[test]
+    fn test_with_context_at_file_edges() {
+        let content = "rust 1\nb\nrust 2";
+        let results = search("rust", content, false, &None, None);
+
+        assert_eq!(
+            vec![(1, "rust 1", false), (2, "b", true), (3, "rust 2", false)],
+            context_summary(with_context(content, results, 3, 3))
+        );
+    }
+
+    #[test]
+    fn test_with_context_extends_beyond_range() {
+        let content = "a\nb\nrust 3\nc\nrust 5\nd\ne\n";
+        let results = search("rust", content, false, &None, Some((3, 5)));
+
         assert_eq!(
-            vec![(1, "Rust:"), (4, "Rusty nails.")],
-            search(query, content, false, &Some(regex))
+            vec![
+                (2, "b", true),
+                (3, "rust 3", false),
+                (4, "c", true),
+                (5, "rust 5", false),
+                (6, "d", true),
+            ],
+            context_summary(with_context(content, results, 1, 1))
         );
     }
+
+    #[test]
+    fn test_ascii_spans_match_unicode_path() {
+        let cases = [
+            ("rust", "Rust and RUST and rUsT"),
+            ("aa", "aaaaa"),
+            ("error", "no match here"),
+            ("x", ""),
+            ("Log-42", "log-42 LOG-42 log-4"),
+        ];
+
+        for (query, line) in cases {
+            let unicode: Vec<Range<usize>> = line
+                .to_lowercase()
+                .match_indices(&query.to_lowercase())
+                .map(|(pos, _)| pos..pos + query.len())
+                .collect();
+
+            assert_eq!(
+                ascii_spans(query, line),
+                unicode,
+                "{:?} in {:?}",
+                query,
+                line
+            );
+            assert_eq!(
+                CompiledQuery::new(query, true, None).is_match(line),
+                line.to_lowercase().contains(&query.to_lowercase())
+            );
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_non_ascii_uses_unicode_path() {
+        assert!(CompiledQuery::new("k", true, None).is_match("\u{212A}elvin"));
+        assert!(CompiledQuery::new("ÉCOLE", true, None).is_match("école"));
+    }
+
+    #[test]
+    fn test_invert_matches() {
+        let content = "rust\nc++\nrust again\ngo\n";
+        let results = search("rust", content, false, &None, None);
+
+        let inverted = invert_matches(content, &results, None);
+        assert!(inverted.iter().all(|result| result.spans.is_empty()));
+        assert_eq!(positions(inverted), vec![(2, 5, "c++"), (4, 20, "go")]);
+
+        let results = search("rust", content, false, &None, Some((2, 3)));
+        let inverted = invert_matches(content, &results, Some((2, 3)));
+        assert_eq!(positions(inverted), vec![(2, 5, "c++")]);
+    }
+
+    #[test]
+    fn test_compiled_query_case_insensitive_spans() {
+        let query = CompiledQuery::new("ÉCOLE", true, None);
+
+        assert!(query.is_match("une école"));
+        assert_eq!(query.spans("une école, une ÉCOLE"), vec![4..10, 16..22]);
+        assert!(!CompiledQuery::new("école", false, None).is_match("ÉCOLE"));
+    }
+
+    #[test]
+    fn test_compiled_query_fuzzy() {
+        let query = CompiledQuery::new("color", false, None).with_fuzzy(Some(1));
+
+        assert!(query.is_match("background-colour: red"));
+        assert!(query.is_match("the colr is off"));
+        assert!(query.is_match("colors"));
+        assert!(!query.is_match("collar of the shirt"));
+        assert!(!query.is_match("cooler"));
+        assert!(query.spans("color").is_empty());
+
+        let query = CompiledQuery::new("Color", true, None).with_fuzzy(Some(1));
+        assert!(query.is_match("COLOUR"));
+
+        let query = CompiledQuery::new("color", false, None).with_fuzzy(Some(0));
+        assert!(query.is_match("color") && !query.is_match("colour"));
+    }
+
+    #[test]
+    fn test_compiled_query_field() {
+        let query = CompiledQuery::new("error", false, None).with_field(Some(3), ',');
+
+        assert!(query.is_match("2024-05-01,web,error,disk full"));
+        assert!(!query.is_match("2024-05-01,error,warn,disk full"));
+        assert!(!query.is_match("2024-05-01,web,info,error in message"));
+        assert!(!query.is_match("error,error"));
+        assert_eq!(query.spans("1,web,error,x"), vec![6..11]);
+
+        let query = CompiledQuery::new("db", false, None).with_field(Some(2), '\t');
+        assert!(query.is_match("a\tdb\tc") && !query.is_match("db\ta\tc"));
+    }
 }
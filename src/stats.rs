@@ -0,0 +1,211 @@
+use crate::{error::ApplicationError, search::LineMatch};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default)]
+pub struct SearchStats {
+    pub files_searched: usize,
+    pub files_with_matches: usize,
+    pub files_without_match: usize,
+    pub lines_printed: usize,
+    pub matched_chars: usize,
+    pub matched_bytes: usize,
+    pub match_histogram: BTreeMap<usize, usize>,
+    pub deadline: Option<Instant>,
+    pub interrupted: Option<&'static AtomicBool>,
+    pub visited_dirs: HashSet<PathBuf>,
+    pub visited_files: HashSet<PathBuf>,
+    pub match_counts: Vec<(String, usize)>,
+    pub progress: Option<Progress>,
+}
+
+/// Rewrites a single status line on `writer` with the number of files searched so far and the
+/// current path, at most once per `interval`.
+pub struct Progress {
+    writer: Box<dyn Write>,
+    interval: Duration,
+    last_update: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(writer: Box<dyn Write>, interval: Duration) -> Self {
+        Progress {
+            writer,
+            interval,
+            last_update: None,
+        }
+    }
+
+    pub fn update(&mut self, files_searched: usize, path: &Path) {
+        let now = Instant::now();
+
+        if self
+            .last_update
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return;
+        }
+
+        self.last_update = Some(now);
+        let _ = write!(
+            self.writer,
+            "\r\x1b[K{} files searched: {}",
+            files_searched,
+            path.display()
+        );
+        let _ = self.writer.flush();
+    }
+
+    pub fn clear(&mut self) {
+        if self.last_update.is_some() {
+            let _ = write!(self.writer, "\r\x1b[K");
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("interval", &self.interval)
+            .field("last_update", &self.last_update)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SearchStats {
+    pub fn top_files(&self, limit: usize) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = self
+            .match_counts
+            .iter()
+            .map(|(path, count)| (path.as_str(), *count))
+            .collect();
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counts.truncate(limit);
+        counts
+    }
+
+    /// Counts `result` as a printed match, adding its length to the `--count-chars` totals and
+    /// its number of matches to the `--match-histogram` buckets.
+    pub fn record_line(&mut self, result: &LineMatch) {
+        self.lines_printed += 1;
+        self.matched_chars += result.line.chars().count();
+        self.matched_bytes += result.line.len();
+        *self.match_histogram.entry(result.spans.len()).or_default() += 1;
+    }
+
+    /// Describes the `--match-histogram` buckets, e.g. `1 match: 40 lines, 2 matches: 5 lines`.
+    pub fn histogram_summary(&self) -> String {
+        let plural = |count: usize, word: &str| {
+            format!("{} {}{}", count, word, if count == 1 { "" } else { "es" })
+        };
+
+        self.match_histogram
+            .iter()
+            .map(|(matches, lines)| {
+                format!(
+                    "{}: {} line{}",
+                    plural(*matches, "match"),
+                    lines,
+                    if *lines == 1 { "" } else { "s" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Fails with `Interrupted` once Ctrl-C has set the flag, so the search stops before the
+    /// next file.
+    pub fn check_interrupted(&self) -> Result<(), ApplicationError> {
+        match self.interrupted {
+            Some(flag) if flag.load(Ordering::SeqCst) => Err(ApplicationError::Interrupted),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn report_progress(&mut self, path: &Path) {
+        if let Some(progress) = &mut self.progress {
+            progress.update(self.files_searched, path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_match(line: &str, matches: usize) -> LineMatch<'_> {
+        LineMatch {
+            line_number: 1,
+            line,
+            byte_offset: 0,
+            spans: (0..matches).map(|index| index..index + 1).collect(),
+            is_context: false,
+        }
+    }
+
+    #[test]
+    fn test_top_files_sorted_by_count_then_path() {
+        let stats = SearchStats {
+            match_counts: vec![
+                ("b.log".to_string(), 3),
+                ("a.log".to_string(), 1),
+                ("d.log".to_string(), 7),
+                ("c.log".to_string(), 3),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            stats.top_files(3),
+            vec![("d.log", 7), ("b.log", 3), ("c.log", 3)]
+        );
+        assert_eq!(stats.top_files(10).len(), 4);
+    }
+
+    #[test]
+    fn test_record_line_counts_chars_and_bytes() {
+        let mut stats = SearchStats::default();
+
+        stats.record_line(&line_match("café", 1));
+        stats.record_line(&line_match("tea", 1));
+
+        assert_eq!(stats.lines_printed, 2);
+        assert_eq!(stats.matched_chars, 7);
+        assert_eq!(stats.matched_bytes, 8);
+    }
+
+    #[test]
+    fn test_histogram_summary() {
+        let mut stats = SearchStats::default();
+        assert_eq!(stats.histogram_summary(), "");
+
+        for matches in [2, 1, 1, 3, 1, 2] {
+            stats.record_line(&line_match("rust", matches));
+        }
+
+        assert_eq!(
+            stats.histogram_summary(),
+            "1 match: 3 lines, 2 matches: 2 lines, 3 matches: 1 line"
+        );
+    }
+
+    #[test]
+    fn test_progress_throttles_updates() {
+        let mut progress = Progress::new(Box::new(Vec::new()), Duration::from_secs(3600));
+
+        progress.update(1, Path::new("a.txt"));
+        let first_update = progress.last_update;
+        progress.update(2, Path::new("b.txt"));
+
+        assert!(first_update.is_some());
+        assert_eq!(progress.last_update, first_update);
+    }
+}
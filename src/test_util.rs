@@ -0,0 +1,12 @@
+//! Helpers shared by the unit tests of several modules.
+
+use std::{env, fs, path::PathBuf, process};
+
+/// Creates an empty directory named after the test and this process under the system temp
+/// directory, removing whatever an earlier run left there.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("greplite-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
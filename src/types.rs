@@ -0,0 +1,71 @@
+use std::path::Path;
+
+const FILE_TYPES: &[(&str, &[&str])] = &[
+    ("c", &["c", "h"]),
+    ("cpp", &["cpp", "cc", "cxx", "hpp", "hh", "hxx"]),
+    ("css", &["css"]),
+    ("go", &["go"]),
+    ("html", &["html", "htm"]),
+    ("java", &["java"]),
+    ("js", &["js", "mjs", "cjs"]),
+    ("json", &["json"]),
+    ("md", &["md", "markdown"]),
+    ("py", &["py", "pyi"]),
+    ("rb", &["rb"]),
+    ("rust", &["rs"]),
+    ("sh", &["sh", "bash"]),
+    ("toml", &["toml"]),
+    ("ts", &["ts", "tsx"]),
+    ("txt", &["txt"]),
+    ("yaml", &["yaml", "yml"]),
+];
+
+pub fn extensions_for(name: &str) -> Option<&'static [&'static str]> {
+    FILE_TYPES
+        .iter()
+        .find(|(type_name, _)| *type_name == name)
+        .map(|(_, extensions)| *extensions)
+}
+
+fn has_type(path: &Path, name: &str) -> bool {
+    let extension = match path.extension() {
+        Some(extension) => extension.to_string_lossy(),
+        None => return false,
+    };
+
+    extensions_for(name).is_some_and(|extensions| extensions.contains(&extension.as_ref()))
+}
+
+pub fn matches_file_types(path: &Path, file_types: &[String], excluded_types: &[String]) -> bool {
+    (file_types.is_empty() || file_types.iter().any(|name| has_type(path, name)))
+        && !excluded_types.iter().any(|name| has_type(path, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extensions_for() {
+        assert_eq!(extensions_for("rust"), Some(&["rs"][..]));
+        assert!(extensions_for("cobol").is_none());
+    }
+
+    #[test]
+    fn test_matches_file_types() {
+        let rust = ["rust".to_string()];
+        let py = ["py".to_string()];
+
+        assert!(matches_file_types(Path::new("src/main.rs"), &rust, &[]));
+        assert!(!matches_file_types(Path::new("setup.py"), &rust, &[]));
+        assert!(!matches_file_types(Path::new("Makefile"), &rust, &[]));
+        assert!(matches_file_types(Path::new("setup.py"), &[], &rust));
+        assert!(!matches_file_types(Path::new("src/main.rs"), &[], &rust));
+        assert!(matches_file_types(
+            Path::new("setup.py"),
+            &["rust".to_string(), "py".to_string()],
+            &[]
+        ));
+        assert!(matches_file_types(Path::new("README"), &[], &py));
+    }
+}